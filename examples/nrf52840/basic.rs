@@ -2,23 +2,21 @@
 #![no_main]
 
 use panic_halt as _;
-use cortex_m::asm;
 use cortex_m_rt::entry;
+use embedded_hal::delay::DelayNs;
 use nrf52840_hal as _;
 use nrf52840_hal::{
+    delay::Delay,
     gpio::{p0::Parts},
-    pac::Peripherals,
+    pac::{CorePeripherals, Peripherals},
     twim::*
 };
 
-use neotrellis_rgb::{ Neotrellis, NeotrellisEventType };
-
-fn delay(microseconds: u32) {
-    asm::delay(64 * microseconds);
-}
+use neotrellis_rgb::{ Color, Neotrellis, NeotrellisEventType, compat::{Eh02Delay, Eh02I2c} };
 
 #[entry]
 fn main() -> ! {
+    let core = CorePeripherals::take().unwrap();
     let peripherals = Peripherals::take().unwrap();
 
     // Initialize our I2C
@@ -31,23 +29,27 @@ fn main() -> ! {
         nrf52840_hal::twim::Frequency::K100
     );
 
-    let mut nt = Neotrellis::new(twim, delay, None);
+    // Use the SysTick based delay so timing does not depend on the clock configuration
+    let delay = Delay::new(core.SYST);
+
+    let mut nt = Neotrellis::new(Eh02I2c::new(twim), Eh02Delay::new(delay), None);
     nt.initialize().unwrap();
 
     loop {
-        delay(20_000);
+        nt.delay.delay_us(20_000);
 
         let mut raw_events: [u8; 16] = [0; 16];
         for event in nt.key_event_iterate(&mut raw_events).unwrap() {
             match event.event_type {
                 NeotrellisEventType::KeyPress =>
-                    nt.set_led(event.key_index as u8, 106, 13, 173).unwrap(),
+                    nt.set_led(event.key_index as u8, Color::PURPLE).unwrap(),
                 NeotrellisEventType::KeyRelease =>
-                    nt.set_led(event.key_index as u8, 0, 0, 0).unwrap()
+                    nt.set_led(event.key_index as u8, Color::BLACK).unwrap(),
+                _ => {}
             };
         }
 
-        delay(500);
+        nt.delay.delay_us(300);
         nt.refresh_leds().unwrap();
     }
 }
@@ -0,0 +1,494 @@
+//! Time-driven LED effects.
+//!
+//! An `Effect` is an object that is advanced with `tick(now_ms, leds)`, where `now_ms` is a
+//! free-running millisecond timestamp that may wrap around, and renders its current frame
+//! into an `Leds` buffer. `Neotrellis` is one, rendering into its framebuffer, so a frame is
+//! shown with `flush`. For a `MultiTrellis`, render into a `[Color; N]` array of the grid's
+//! `WIDTH * HEIGHT` LEDs, indexed `y * WIDTH + x`, and send it with `set_led`.
+//!
+//! Every effect can be limited to a subset of the keys with `on_keys`, which renders it as
+//! if the LEDs of the subset were the only ones, in index order. The subset is a bitset of
+//! as many 64-bit words as the grid needs, so it can cover every key of a `MultiTrellis`.
+//! Effects on disjoint subsets can be ticked one after another into the same buffer.
+//!
+//! `Reactive` effects light the keys in response to keypad events, which are passed to their
+//! `update`: `Ripple`, `Afterglow` and `NeighborHighlight`.
+//!
+//! Example usage: a rainbow on the top row and a breathing red everywhere else:
+//! ```ignore
+//! let mut rainbow = Rainbow::new(2_000).on_keys([0x000F]);
+//! let mut pulse = Pulse::new(Color::RED, 1_500).on_keys([0xFFF0]);
+//!
+//! loop {
+//!     let now = timer.now_ms();
+//!     rainbow.tick(now, &mut nt);
+//!     pulse.tick(now, &mut nt);
+//!     nt.flush().unwrap();
+//!
+//!     nt.delay.delay_ms(20);
+//! }
+//! ```
+
+use crate::Color;
+
+mod reactive;
+pub use reactive::{Afterglow, NeighborHighlight, Reactive, Ripple};
+
+/// A set of LEDs that effects render into.
+pub trait Leds {
+    /// The number of LEDs, which are indexed from 0.
+    fn led_count(&self) -> usize;
+
+    /// The color of an LED, or black if `index` is not less than `led_count`.
+    fn color(&self, index: usize) -> Color;
+
+    /// Sets the color of an LED. Indices that are not less than `led_count` are ignored.
+    fn set_color(&mut self, index: usize, color: Color);
+}
+
+impl Leds for [Color] {
+    fn led_count(&self) -> usize {
+        self.len()
+    }
+
+    fn color(&self, index: usize) -> Color {
+        self.get(index).copied().unwrap_or_default()
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        if let Some(led) = self.get_mut(index) { *led = color; }
+    }
+}
+
+impl <const N: usize> Leds for [Color; N] {
+    fn led_count(&self) -> usize {
+        N
+    }
+
+    fn color(&self, index: usize) -> Color {
+        self[..].color(index)
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        self[..].set_color(index, color)
+    }
+}
+
+impl <I2C: embedded_hal::i2c::I2c, D: embedded_hal::delay::DelayNs> Leds for crate::Neotrellis<I2C, D> {
+    fn led_count(&self) -> usize {
+        16
+    }
+
+    fn color(&self, index: usize) -> Color {
+        if index >= 16 { return Color::BLACK; }
+        self.pixel(index as u8).unwrap_or_default()
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        if index < 16 { self.set_pixel(index as u8, color).ok(); }
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> Leds for crate::asynch::Neotrellis<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs
+{
+    fn led_count(&self) -> usize {
+        16
+    }
+
+    fn color(&self, index: usize) -> Color {
+        if index >= 16 { return Color::BLACK; }
+        self.pixel(index as u8).unwrap_or_default()
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        if index < 16 { self.set_pixel(index as u8, color).ok(); }
+    }
+}
+
+/// An animation that renders a frame for any point in time.
+pub trait Effect {
+    /// Advances the effect to `now_ms` and renders it into `leds`.
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds);
+
+    /// Limits the effect to the LEDs whose bits are set in `keys`, where LED n is bit
+    /// `n % 64` of `keys[n / 64]`: e.g. `[0x000F]` for the top row of a Neotrellis, or four
+    /// words for the 256 LEDs of a 4x4 `MultiTrellis`.
+    fn on_keys<const WORDS: usize>(self, keys: [u64; WORDS]) -> OnKeys<Self, WORDS>
+    where
+        Self: Sized
+    {
+        OnKeys { effect: self, keys }
+    }
+}
+
+/// An effect limited to a subset of the keys, created with `Effect::on_keys`.
+pub struct OnKeys<E, const WORDS: usize = 1> {
+    effect: E,
+    keys: [u64; WORDS]
+}
+
+impl <E, const WORDS: usize> OnKeys<E, WORDS> {
+    /// The effect running on the subset.
+    pub fn effect(&mut self) -> &mut E {
+        &mut self.effect
+    }
+}
+
+impl <E: Effect, const WORDS: usize> Effect for OnKeys<E, WORDS> {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let mut keys = self.keys;
+        for (word, bits) in keys.iter_mut().enumerate() {
+            *bits &= low_bits(leds.led_count().saturating_sub(word * 64));
+        }
+
+        self.effect.tick(now_ms, &mut Subset { leds, keys: &keys });
+    }
+}
+
+/// A view of the LEDs whose bits are set in `keys`, renumbered from 0.
+struct Subset<'a, 'b> {
+    leds: &'a mut dyn Leds,
+    keys: &'b [u64]
+}
+
+impl <'a, 'b> Subset<'a, 'b> {
+    fn led_index(&self, mut index: usize) -> Option<usize> {
+        for (word, &bits) in self.keys.iter().enumerate() {
+            let count = bits.count_ones() as usize;
+            if index >= count {
+                index -= count;
+                continue;
+            }
+
+            let mut bits = bits;
+            for _ in 0..index { bits &= bits - 1; }
+            return Some(word * 64 + bits.trailing_zeros() as usize);
+        }
+
+        None
+    }
+}
+
+impl <'a, 'b> Leds for Subset<'a, 'b> {
+    fn led_count(&self) -> usize {
+        self.keys.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    fn color(&self, index: usize) -> Color {
+        self.led_index(index).map_or(Color::BLACK, |index| self.leds.color(index))
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        if let Some(index) = self.led_index(index) { self.leds.set_color(index, color); }
+    }
+}
+
+fn low_bits(count: usize) -> u64 {
+    if count >= 64 { u64::MAX } else { (1 << count) - 1 }
+}
+
+fn fill(leds: &mut dyn Leds, color: Color) {
+    for index in 0..leds.led_count() { leds.set_color(index, color); }
+}
+
+/// How far `elapsed_ms` is through `duration_ms`, from 0 to 255.
+fn progress(elapsed_ms: u32, duration_ms: u32) -> u8 {
+    if elapsed_ms >= duration_ms { return 255; }
+    (elapsed_ms as u64 * 255 / duration_ms as u64) as u8
+}
+
+/// Fades every LED from one color to another over `duration_ms`, then holds the second
+/// color. The fade starts at the first tick.
+pub struct Fade {
+    /// The color at the start of the fade.
+    pub from: Color,
+
+    /// The color at the end of the fade, which is held once it is reached.
+    pub to: Color,
+
+    /// How long the fade takes.
+    pub duration_ms: u32,
+
+    started_at: Option<u32>,
+    finished: bool
+}
+
+impl Fade {
+    /// Creates a fade from `from` to `to` that starts at the first tick.
+    pub fn new(from: Color, to: Color, duration_ms: u32) -> Self {
+        Fade { from, to, duration_ms, started_at: None, finished: false }
+    }
+
+    /// Starts the fade again from `from` at the next tick.
+    pub fn restart(&mut self) {
+        self.started_at = None;
+        self.finished = false;
+    }
+
+    /// Returns true once a tick has rendered the `to` color.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl Effect for Fade {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let started_at = *self.started_at.get_or_insert(now_ms);
+        let amount = progress(now_ms.wrapping_sub(started_at), self.duration_ms);
+        self.finished = amount == 255;
+        fill(leds, self.from.blend(self.to, amount));
+    }
+}
+
+/// Breathes every LED in a color, from off up to full and back down once every `period_ms`.
+pub struct Pulse {
+    /// The color at the top of each breath.
+    pub color: Color,
+
+    /// The time of one whole breath, up and back down.
+    pub period_ms: u32
+}
+
+impl Pulse {
+    /// Creates a pulse of `color` that breathes once every `period_ms`.
+    pub fn new(color: Color, period_ms: u32) -> Self {
+        Pulse { color, period_ms }
+    }
+}
+
+impl Effect for Pulse {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let period = self.period_ms.max(2);
+        let (phase, half) = (now_ms % period, period / 2);
+        let level = if phase < half { progress(phase, half) } else { progress(period - phase, period - half) };
+        fill(leds, self.color.scale(level));
+    }
+}
+
+/// Spreads the color wheel across the LEDs and turns it once every `period_ms`.
+pub struct Rainbow {
+    /// The time the color wheel takes to turn once.
+    pub period_ms: u32,
+
+    /// The saturation of the colors, as in `Color::from_hsv`.
+    pub saturation: u8,
+
+    /// The brightness of the colors, as in `Color::from_hsv`.
+    pub value: u8
+}
+
+impl Rainbow {
+    /// Creates a rainbow of fully saturated colors at full brightness.
+    pub fn new(period_ms: u32) -> Self {
+        Rainbow { period_ms, saturation: 255, value: 255 }
+    }
+}
+
+impl Effect for Rainbow {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let count = leds.led_count();
+        let turn = progress(now_ms % self.period_ms.max(1), self.period_ms) as usize;
+        for index in 0..count {
+            let hue = (turn + index * 256 / count) as u8;
+            leds.set_color(index, Color::from_hsv(hue, self.saturation, self.value));
+        }
+    }
+}
+
+/// Moves a run of `length` lit LEDs along the LEDs in index order, one LED every `step_ms`,
+/// wrapping around at the end.
+pub struct Chase {
+    /// The color of the lit LEDs.
+    pub color: Color,
+
+    /// The color of the other LEDs.
+    pub background: Color,
+
+    /// The time the run stays on each LED.
+    pub step_ms: u32,
+
+    /// The number of lit LEDs.
+    pub length: usize
+}
+
+impl Chase {
+    /// Creates a chase of a single LED on a black background.
+    pub fn new(color: Color, step_ms: u32) -> Self {
+        Chase { color, background: Color::BLACK, step_ms, length: 1 }
+    }
+}
+
+impl Effect for Chase {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let count = leds.led_count();
+        if count == 0 { return; }
+
+        let position = (now_ms / self.step_ms.max(1)) as usize % count;
+        for index in 0..count {
+            let lit = (index + count - position) % count < self.length;
+            leds.set_color(index, if lit { self.color } else { self.background });
+        }
+    }
+}
+
+/// Lights a random LED every `interval_ms`, and fades every lit LED back to the background
+/// by `decay` (out of 255) at each interval.
+pub struct Sparkle {
+    /// The color of a new sparkle.
+    pub color: Color,
+
+    /// The color the sparkles fade back to.
+    pub background: Color,
+
+    /// The time between sparkles.
+    pub interval_ms: u32,
+
+    /// How far the LEDs fade back to the background at each interval, out of 255.
+    pub decay: u8,
+
+    random: u32,
+    last_ms: Option<u32>
+}
+
+impl Sparkle {
+    /// Creates a sparkle on a black background. `seed` starts the pseudo-random sequence
+    /// of LEDs, e.g. from a hardware random number generator or the time of startup.
+    pub fn new(color: Color, interval_ms: u32, seed: u32) -> Self {
+        Sparkle { color, background: Color::BLACK, interval_ms, decay: 64, random: seed.max(1), last_ms: None }
+    }
+
+    /// The next number of an xorshift sequence.
+    fn next_random(&mut self) -> u32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 17;
+        self.random ^= self.random << 5;
+        self.random
+    }
+}
+
+impl Effect for Sparkle {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let last_ms = match self.last_ms {
+            Some(last_ms) => last_ms,
+            None => {
+                self.last_ms = Some(now_ms);
+                return fill(leds, self.background);
+            }
+        };
+
+        let count = leds.led_count();
+        let interval = self.interval_ms.max(1);
+        let steps = now_ms.wrapping_sub(last_ms) / interval;
+        self.last_ms = Some(last_ms.wrapping_add(steps * interval));
+        if count == 0 { return; }
+
+        // After a long gap, the oldest sparkles would have faded out anyway
+        for _ in 0..steps.min(16) {
+            for index in 0..count {
+                leds.set_color(index, leds.color(index).blend(self.background, self.decay));
+            }
+
+            let index = self.next_random() as usize % count;
+            leds.set_color(index, self.color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+
+    #[test]
+    fn fade_blends_over_its_duration_and_holds_the_target() {
+        let mut leds = [Color::RED; 4];
+        let mut fade = Fade::new(Color::BLACK, Color::WHITE, 100);
+
+        fade.tick(u32::MAX - 10, &mut leds);
+        assert_eq!(leds, [Color::BLACK; 4]);
+
+        fade.tick(39, &mut leds);
+        assert_eq!(leds[3], Color::rgb(127, 127, 127));
+        assert!(!fade.is_finished());
+
+        fade.tick(500, &mut leds);
+        assert_eq!(leds, [Color::WHITE; 4]);
+        assert!(fade.is_finished());
+    }
+
+    #[test]
+    fn periodic_effects_follow_the_clock() {
+        let mut leds = [Color::BLACK; 4];
+
+        let mut pulse = Pulse::new(Color::RED, 1000);
+        pulse.tick(2000, &mut leds);
+        assert_eq!(leds[0], Color::BLACK);
+        pulse.tick(2500, &mut leds);
+        assert_eq!(leds[0], Color::RED);
+
+        Rainbow::new(1000).tick(0, &mut leds);
+        assert_eq!(leds, [Color::RED, Color::from_hsv(64, 255, 255), Color::from_hsv(128, 255, 255), Color::from_hsv(192, 255, 255)]);
+
+        let mut chase = Chase::new(Color::BLUE, 10);
+        chase.length = 2;
+        chase.tick(35, &mut leds);
+        assert_eq!(leds, [Color::BLUE, Color::BLACK, Color::BLACK, Color::BLUE]);
+    }
+
+    #[test]
+    fn effects_on_keys_only_touch_their_subset() {
+        let mut leds = [Color::WHITE; 16];
+        let mut chase = Chase::new(Color::GREEN, 10).on_keys([0b1010_0000_0000_0101]);
+
+        chase.tick(10, &mut leds);
+        let lit: std::vec::Vec<_> = (0..16).filter(|&index| leds[index] == Color::GREEN).collect();
+        let cleared: std::vec::Vec<_> = (0..16).filter(|&index| leds[index] == Color::BLACK).collect();
+        assert_eq!(lit, [2]);
+        assert_eq!(cleared, [0, 13, 15]);
+        assert_eq!(leds[1], Color::WHITE);
+    }
+
+    #[test]
+    fn subsets_reach_past_the_first_64_leds() {
+        let mut leds = [Color::BLACK; 12 * 8];
+        let mut red = Fade::new(Color::RED, Color::RED, 0).on_keys([1 << 3, 1 << 31, u64::MAX]);
+
+        red.tick(0, &mut leds);
+        let lit: std::vec::Vec<_> = (0..leds.len()).filter(|&index| leds[index] == Color::RED).collect();
+        assert_eq!(lit, [3, 95]);
+    }
+
+    #[test]
+    fn sparkle_lights_one_led_per_interval_and_decays() {
+        let mut leds = [Color::WHITE; 8];
+        let mut sparkle = Sparkle::new(Color::WHITE, 50, 1234);
+        sparkle.decay = 255;
+
+        sparkle.tick(0, &mut leds);
+        assert_eq!(leds, [Color::BLACK; 8]);
+
+        sparkle.tick(49, &mut leds);
+        assert_eq!(leds, [Color::BLACK; 8]);
+
+        sparkle.tick(60, &mut leds);
+        assert_eq!(leds.iter().filter(|&&color| color == Color::WHITE).count(), 1);
+    }
+
+    #[test]
+    fn effects_render_into_the_neotrellis_framebuffer() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        Chase::new(Color::PURPLE, 100).on_keys([0xF000]).tick(200, &mut nt);
+        nt.flush().unwrap();
+
+        let bus = bus.borrow();
+        let board = bus.board(0x2E).unwrap();
+        assert_eq!(board.pixel(14), (106, 13, 173));
+        assert_eq!(board.pixel(12), (0, 0, 0));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{Color, MultiTrellisEvent, NeotrellisEvent, NeotrellisEventType};
+
+use super::{progress, Effect, Leds};
+
+/// An effect that reacts to the keys being pressed and released.
+///
+/// Keys are numbered like the LEDs they light, row by row in a grid `columns()` keys
+/// wide: the `key_index` of a `NeotrellisEvent`, or `y * columns() + x` for a
+/// `MultiTrellisEvent` given to `update_at`.
+///
+/// Example usage: an afterglow behind every key that is let go:
+/// ```ignore
+/// let mut glow = Afterglow::<16>::new(Color::PURPLE, 800);
+///
+/// loop {
+///     let now = timer.now_ms();
+///     for event in nt.key_event_iterate(&mut raw_events).unwrap() {
+///         glow.update(&event, now);
+///     }
+///
+///     glow.tick(now, &mut nt);
+///     nt.flush().unwrap();
+/// }
+/// ```
+pub trait Reactive: Effect {
+    /// The number of keys in each row of the grid the effect lights.
+    fn columns(&self) -> usize;
+
+    /// Reacts to `key` being pressed at `now_ms`.
+    fn key_pressed(&mut self, key: usize, now_ms: u32);
+
+    /// Reacts to `key` being released at `now_ms`.
+    fn key_released(&mut self, key: usize, now_ms: u32);
+
+    /// Reacts to a keypad event read at `now_ms`. `KeyHigh` and `KeyLow` level events are
+    /// ignored, as they repeat while the key stays in the same state.
+    fn update(&mut self, event: &NeotrellisEvent, now_ms: u32) {
+        match event.event_type {
+            NeotrellisEventType::KeyPress => self.key_pressed(event.key_index, now_ms),
+            NeotrellisEventType::KeyRelease => self.key_released(event.key_index, now_ms),
+            _ => {}
+        }
+    }
+
+    /// Reacts to an event of a `MultiTrellis` grid read at `now_ms`. The effect's `columns`
+    /// should be the grid's `WIDTH`. Level events are ignored, as in `update`.
+    fn update_at(&mut self, event: &MultiTrellisEvent, now_ms: u32) {
+        let key_index = event.y * self.columns() + event.x;
+        self.update(&NeotrellisEvent { key_index, event_type: event.event_type }, now_ms);
+    }
+}
+
+/// The (x, y) position of a key in a grid `columns` keys wide.
+fn position(key: usize, columns: usize) -> (usize, usize) {
+    (key % columns, key / columns)
+}
+
+/// The number of ripples that can spread at once. A new ripple replaces the oldest one.
+const RIPPLES: usize = 4;
+
+/// Sends a ring of light outward from each pressed key, one key further every `step_ms`.
+/// The ring dims as it spreads and is gone once it is more than `radius` keys away.
+pub struct Ripple {
+    /// The color of a ring as it leaves its key.
+    pub color: Color,
+
+    /// The color of the LEDs that no ring is passing.
+    pub background: Color,
+
+    /// The number of keys in each row of the grid.
+    pub columns: usize,
+
+    /// The time a ring takes to move one key further.
+    pub step_ms: u32,
+
+    /// The farthest a ring spreads, in keys.
+    pub radius: usize,
+
+    ripples: [Option<(usize, u32)>; RIPPLES],
+    next: usize
+}
+
+impl Ripple {
+    /// Creates a ripple on a black background that spreads across a 4x4 board.
+    pub fn new(color: Color, step_ms: u32) -> Self {
+        Ripple { color, background: Color::BLACK, columns: 4, step_ms, radius: 3, ripples: [None; RIPPLES], next: 0 }
+    }
+
+    /// How many keys a ring started at `started_at` has spread by `now_ms`.
+    fn distance(&self, started_at: u32, now_ms: u32) -> usize {
+        (now_ms.wrapping_sub(started_at) / self.step_ms.max(1)) as usize
+    }
+}
+
+impl Effect for Ripple {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        let columns = self.columns.max(1);
+
+        // Forget the rings that have spread past the radius
+        for index in 0..RIPPLES {
+            if let Some((_, started_at)) = self.ripples[index] {
+                if self.distance(started_at, now_ms) > self.radius { self.ripples[index] = None; }
+            }
+        }
+
+        for index in 0..leds.led_count() {
+            let (led_x, led_y) = position(index, columns);
+            let level = self.ripples.iter().flatten().filter_map(|&(key, started_at)| {
+                let distance = self.distance(started_at, now_ms);
+                let (x, y) = position(key, columns);
+                let ring = led_x.abs_diff(x).max(led_y.abs_diff(y));
+                if ring != distance { return None; }
+
+                Some(255 - progress(distance as u32, self.radius as u32 + 1))
+            }).max().unwrap_or(0);
+
+            leds.set_color(index, self.background.blend(self.color, level));
+        }
+    }
+}
+
+impl Reactive for Ripple {
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn key_pressed(&mut self, key: usize, now_ms: u32) {
+        self.ripples[self.next] = Some((key, now_ms));
+        self.next = (self.next + 1) % RIPPLES;
+    }
+
+    fn key_released(&mut self, _key: usize, _now_ms: u32) {}
+}
+
+/// Lights each key while it is held, then fades it back to the background over `decay_ms`
+/// once it is released. Tracks the first `KEYS` keys.
+pub struct Afterglow<const KEYS: usize = 16> {
+    /// The color of a held key.
+    pub color: Color,
+
+    /// The color of the keys that are not lit.
+    pub background: Color,
+
+    /// The number of keys in each row of the grid.
+    pub columns: usize,
+
+    /// The time a released key takes to fade out.
+    pub decay_ms: u32,
+
+    held: [bool; KEYS],
+    released_at: [Option<u32>; KEYS]
+}
+
+impl <const KEYS: usize> Afterglow<KEYS> {
+    /// Creates an afterglow for a 4x4 board on a black background.
+    pub fn new(color: Color, decay_ms: u32) -> Self {
+        Afterglow { color, background: Color::BLACK, columns: 4, decay_ms, held: [false; KEYS], released_at: [None; KEYS] }
+    }
+}
+
+impl <const KEYS: usize> Effect for Afterglow<KEYS> {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        for key in 0..KEYS.min(leds.led_count()) {
+            let level = match self.released_at[key] {
+                _ if self.held[key] => 255,
+                Some(released_at) => {
+                    let level = 255 - progress(now_ms.wrapping_sub(released_at), self.decay_ms);
+                    if level == 0 { self.released_at[key] = None; }
+                    level
+                },
+                None => 0
+            };
+
+            leds.set_color(key, self.background.blend(self.color, level));
+        }
+    }
+}
+
+impl <const KEYS: usize> Reactive for Afterglow<KEYS> {
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn key_pressed(&mut self, key: usize, _now_ms: u32) {
+        if key >= KEYS { return; }
+        self.held[key] = true;
+        self.released_at[key] = None;
+    }
+
+    fn key_released(&mut self, key: usize, now_ms: u32) {
+        if key >= KEYS || !self.held[key] { return; }
+        self.held[key] = false;
+        self.released_at[key] = Some(now_ms);
+    }
+}
+
+/// Lights each held key and, more softly, the keys above, below and beside it. Tracks the
+/// first `KEYS` keys.
+pub struct NeighborHighlight<const KEYS: usize = 16> {
+    /// The color of a held key.
+    pub color: Color,
+
+    /// The color of the neighbors of a held key.
+    pub neighbor_color: Color,
+
+    /// The color of the other keys.
+    pub background: Color,
+
+    /// The number of keys in each row of the grid.
+    pub columns: usize,
+
+    held: [bool; KEYS]
+}
+
+impl <const KEYS: usize> NeighborHighlight<KEYS> {
+    /// Creates a highlight for a 4x4 board on a black background, with the neighbors lit at
+    /// a quarter of `color`.
+    pub fn new(color: Color) -> Self {
+        NeighborHighlight { color, neighbor_color: color.scale(64), background: Color::BLACK, columns: 4, held: [false; KEYS] }
+    }
+
+    fn is_neighbor_held(&self, key: usize) -> bool {
+        let columns = self.columns.max(1);
+        let (x, y) = position(key, columns);
+        self.held.iter().enumerate().any(|(other, &held)| {
+            let (other_x, other_y) = position(other, columns);
+            held && x.abs_diff(other_x) + y.abs_diff(other_y) == 1
+        })
+    }
+}
+
+impl <const KEYS: usize> Effect for NeighborHighlight<KEYS> {
+    fn tick(&mut self, _now_ms: u32, leds: &mut dyn Leds) {
+        for key in 0..KEYS.min(leds.led_count()) {
+            let color = if self.held[key] {
+                self.color
+            } else if self.is_neighbor_held(key) {
+                self.neighbor_color
+            } else {
+                self.background
+            };
+
+            leds.set_color(key, color);
+        }
+    }
+}
+
+impl <const KEYS: usize> Reactive for NeighborHighlight<KEYS> {
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn key_pressed(&mut self, key: usize, _now_ms: u32) {
+        if key < KEYS { self.held[key] = true; }
+    }
+
+    fn key_released(&mut self, key: usize, _now_ms: u32) {
+        if key < KEYS { self.held[key] = false; }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{press, release};
+
+    fn lit(leds: &[Color; 16]) -> std::vec::Vec<usize> {
+        (0..16).filter(|&index| leds[index] != Color::BLACK).collect()
+    }
+
+    #[test]
+    fn ripple_spreads_outward_and_dims() {
+        let mut leds = [Color::BLACK; 16];
+        let mut ripple = Ripple::new(Color::WHITE, 100);
+        ripple.update(&press(5), 1_000);
+
+        ripple.tick(1_050, &mut leds);
+        assert_eq!(lit(&leds), [5]);
+        assert_eq!(leds[5], Color::WHITE);
+
+        ripple.tick(1_100, &mut leds);
+        assert_eq!(lit(&leds), [0, 1, 2, 4, 6, 8, 9, 10]);
+        assert_eq!(leds[0], Color::rgb(192, 192, 192));
+
+        ripple.tick(1_400, &mut leds);
+        assert!(lit(&leds).is_empty());
+    }
+
+    #[test]
+    fn ripple_lights_every_led_of_a_large_grid() {
+        let mut leds = [Color::BLACK; 12 * 8];
+        let mut ripple = Ripple::new(Color::WHITE, 100);
+        ripple.columns = 12;
+        ripple.update_at(&MultiTrellisEvent { x: 10, y: 7, event_type: NeotrellisEventType::KeyPress }, 0);
+        ripple.update_at(&MultiTrellisEvent { x: 0, y: 0, event_type: NeotrellisEventType::KeyHigh }, 0);
+
+        ripple.tick(0, &mut leds);
+        assert_eq!(leds.iter().filter(|&&color| color != Color::BLACK).count(), 1);
+        assert_eq!(leds[7 * 12 + 10], Color::WHITE);
+
+        ripple.tick(100, &mut leds);
+        assert_eq!(leds[6 * 12 + 11], Color::rgb(192, 192, 192));
+    }
+
+    #[test]
+    fn afterglow_fades_after_release() {
+        let mut leds = [Color::BLACK; 16];
+        let mut glow = Afterglow::<16>::new(Color::RED, 200);
+
+        glow.update(&press(3), 0);
+        glow.tick(5_000, &mut leds);
+        assert_eq!(leds[3], Color::RED);
+
+        glow.update(&release(3), 5_000);
+        glow.tick(5_100, &mut leds);
+        assert_eq!(leds[3], Color::rgb(128, 0, 0));
+
+        glow.tick(5_200, &mut leds);
+        assert!(lit(&leds).is_empty());
+    }
+
+    #[test]
+    fn neighbor_highlight_lights_the_keys_around_held_ones() {
+        let mut leds = [Color::BLACK; 16];
+        let mut highlight = NeighborHighlight::<16>::new(Color::GREEN);
+
+        highlight.update(&press(0), 0);
+        highlight.update(&press(15), 0);
+        highlight.update(&release(15), 0);
+        highlight.tick(0, &mut leds);
+
+        assert_eq!(lit(&leds), [0, 1, 4]);
+        assert_eq!(leds[0], Color::GREEN);
+        assert_eq!(leds[1], Color::rgb(0, 64, 0));
+    }
+}
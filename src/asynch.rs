@@ -0,0 +1,442 @@
+//! An async Neotrellis driver built on the `embedded-hal-async` traits.
+//!
+//! This mirrors the blocking `Neotrellis` driver, but the delays that the Seesaw requires
+//! between writes and reads are awaited on the given `DelayNs` implementation rather than
+//! spun on, so other tasks can run while the board prepares its response. As with the
+//! blocking driver, the status and GPIO functions come from an async `Seesaw` core that the
+//! `Neotrellis` dereferences to.
+//!
+//! Example usage: turning an LED purple while it is pressed:
+//! ```ignore
+//! let mut nt = Neotrellis::new(i2c, Delay, None);
+//! nt.initialize().await.unwrap();
+//!
+//! loop {
+//!     Timer::after_millis(20).await;
+//!
+//!     let mut raw_events: [u8; 16] = [0; 16];
+//!     for event in nt.key_event_iterate(&mut raw_events).await.unwrap() {
+//!         match event.event_type {
+//!             NeotrellisEventType::KeyPress =>
+//!                 nt.set_led(event.key_index as u8, Color::PURPLE).await.unwrap(),
+//!             NeotrellisEventType::KeyRelease =>
+//!                 nt.set_led(event.key_index as u8, Color::BLACK).await.unwrap(),
+//!             _ => {}
+//!         };
+//!     }
+//!
+//!     nt.delay.delay_us(300).await;
+//!     nt.refresh_leds().await.unwrap();
+//! }
+//! ```
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::color::ColorCorrection;
+use crate::framebuffer::Framebuffer;
+use crate::seesaw::{
+    KEYPAD_REGISTER_BASE,
+    NEOPIXEL_REGISTER_BASE,
+    READ_DELAY_US,
+    KeypadRegister,
+    NeopixelRegister
+};
+use crate::{
+    Color,
+    DrainReport,
+    KeyEdges,
+    NeotrellisError,
+    NeotrellisEvent,
+    NeotrellisEventIterator,
+    DEFAULT_ADDRESS,
+    HARDWARE_ID
+};
+
+pub(crate) mod seesaw;
+pub use self::seesaw::Seesaw;
+
+/// Async counterpart of the `Neotrellis` structure.
+///
+/// Like the blocking driver, it dereferences to its `Seesaw` core, so the status and GPIO
+/// functions and the public `delay` are available on it directly.
+pub struct Neotrellis<I2C, D> {
+    seesaw: Seesaw<I2C, D>,
+    framebuffer: Framebuffer,
+    correction: ColorCorrection
+}
+
+impl <I2C: I2c, D: DelayNs> Neotrellis<I2C, D> {
+    /// Creates a new async Neotrellis struct which can be used to communicate with the
+    /// neotrellis board.
+    ///
+    /// The address will default to 0x2E (the default address with no jumpers). If the Neotrellis
+    /// has another address due to jumpers, it can be given in the `custom_address` parameter.
+    pub fn new(i2c: I2C, delay: D, custom_address: Option<u8>) -> Self {
+        Neotrellis {
+            seesaw: Seesaw::new(i2c, delay, custom_address.unwrap_or(DEFAULT_ADDRESS)),
+            framebuffer: Framebuffer::new(),
+            correction: ColorCorrection::NONE
+        }
+    }
+
+    /// Consumes the Neotrellis and hands back the I2C bus and delay it was created with.
+    pub fn release(self) -> (I2C, D) {
+        self.seesaw.release()
+    }
+
+    /// Checks if the Neotrellis board is communicable and sends several commands to initialize
+    /// it to use its keypad and LED array. Like the blocking version, this function returns
+    /// `NeotrellisError::WrongHardwareId` if another device answers at the address.
+    pub async fn initialize(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        // Make sure it is working
+        let id = self.seesaw.hardware_id().await?;
+        if id != HARDWARE_ID { return Err(NeotrellisError::WrongHardwareId(id)); }
+
+        // Activate all keys on the trellis
+        for key in 0..16 {
+            self.configure_key(key, KeyEdges::RISING | KeyEdges::FALLING, true).await?;
+        }
+
+        // Start up and clear the leds
+        self.enable_leds().await?;
+        self.clear_leds().await?;
+        self.seesaw.delay.delay_us(300).await;
+        self.refresh_leds().await
+    }
+
+    /// Checks how many events have been generated by the Neotrellis keypad, retrieves
+    /// them in their raw format into `event_buffer`, and returns an iterator over them.
+    pub async fn key_event_iterate<'a>(&mut self, event_buffer: &'a mut [u8]) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        let count = self.key_event_count().await?;
+        if count == 0 || event_buffer.is_empty() {
+            return Ok(NeotrellisEventIterator::new(&[], count as usize));
+        }
+
+        self.key_event_iterate_write().await?;
+        self.seesaw.delay.delay_us(READ_DELAY_US).await;
+        self.key_event_iterate_read(event_buffer, count).await
+    }
+
+    /// Empties the keypad FIFO, reading it in chunks the size of `event_buffer` and handing
+    /// each event to `on_event`, like the blocking `key_event_drain`. Returns
+    /// `NeotrellisError::BufferTooSmall` if `event_buffer` is empty.
+    pub async fn key_event_drain<F: FnMut(NeotrellisEvent)>(&mut self, event_buffer: &mut [u8], mut on_event: F) -> Result<DrainReport, NeotrellisError<I2C::Error>> {
+        if event_buffer.is_empty() { return Err(NeotrellisError::BufferTooSmall); }
+
+        let mut report = DrainReport::default();
+        for count in crate::drain_chunks(self.key_event_count().await?, event_buffer.len()) {
+            self.key_event_iterate_write().await?;
+            self.seesaw.delay.delay_us(READ_DELAY_US).await;
+            let events = self.key_event_iterate_read(event_buffer, count).await?;
+            report.record(events.dropped(), events, &mut on_event);
+        }
+
+        Ok(report)
+    }
+
+    /// Waits for `interrupt_pin`, connected to the Neotrellis INT line, to go low and then
+    /// reads the pending events like `key_event_iterate`. The bus is left free while waiting.
+    ///
+    /// The keypad interrupt must have been enabled with `enable_key_interrupt` first.
+    pub async fn wait_for_key_events<'a, P: Wait>(&mut self, interrupt_pin: &mut P, event_buffer: &'a mut [u8]) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        interrupt_pin.wait_for_low().await.map_err(|_| NeotrellisError::Pin)?;
+        self.key_event_iterate(event_buffer).await
+    }
+
+    /// Enables or disables events for the given `edges` of a key. Edges that are not in
+    /// `edges` keep their current setting. Returns `NeotrellisError::InvalidKey` if
+    /// `key_index` is not in 0-15.
+    pub async fn configure_key(&mut self, key_index: usize, edges: KeyEdges, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        if key_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+        self.write(&crate::configure_key_command(key_index, edges, enabled)).await
+    }
+
+    /// Makes the Neotrellis pull its INT line low while there are events in the keypad FIFO.
+    pub async fn enable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.write(&crate::key_interrupt_command(true)).await
+    }
+
+    /// Stops the Neotrellis from pulling its INT line low for keypad events.
+    pub async fn disable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.write(&crate::key_interrupt_command(false)).await
+    }
+
+    /// Returns how many events have been generated by the Neotrellis keypad.
+    pub async fn key_event_count(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        self.key_event_count_write().await?;
+        self.seesaw.delay.delay_us(READ_DELAY_US).await;
+        self.key_event_count_read().await
+    }
+
+    /// A write operation that sets the next read to see how many keypad events are in the FIFO.
+    pub async fn key_event_count_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Count as u8];
+        self.write(&command).await
+    }
+
+    /// The read operation corresponding to `key_event_count_write`.
+    pub async fn key_event_count_read(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        self.read_byte().await
+    }
+
+    /// A write operation that sets the next read to the keypad event buffer.
+    pub async fn key_event_iterate_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Fifo as u8];
+        self.write(&command).await
+    }
+
+    /// A read event that reads the events out to `event_buffer` and returns
+    /// an iterator to these events.
+    ///
+    /// At most `event_buffer.len()` of the `count` events are read. The rest are left in
+    /// the FIFO and reported by the iterator's `deferred`.
+    pub async fn key_event_iterate_read<'a>(&mut self, event_buffer: &'a mut [u8], count: u8) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        let (raw_events, deferred) = crate::event_chunk(event_buffer, count);
+        if !raw_events.is_empty() { self.read(raw_events).await?; }
+        Ok(NeotrellisEventIterator::new(raw_events, deferred))
+    }
+
+    /// Sets all LEDs on the Neotrellis to an RGB value of #000000
+    pub async fn clear_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for command in crate::clear_leds_commands().iter() { self.write(command).await? }
+
+        self.framebuffer.clear();
+        Ok(())
+    }
+
+    /// Sets an LED on the Neotrellis to a color. Returns `NeotrellisError::InvalidKey`
+    /// if `led_index` is not in 0-15.
+    pub async fn set_led(&mut self, led_index: u8, color: impl Into<Color>) -> Result<(), NeotrellisError<I2C::Error>> {
+        if led_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+        let color = color.into();
+        self.write(&crate::set_led_command(led_index, self.correction.apply(color))).await?;
+
+        self.framebuffer.store_pixel(led_index as usize, color);
+        Ok(())
+    }
+
+    /// Refreshes the LED display based on changes made in `clear_leds` and `set_led`.
+    /// Should be called 300us after these functions to allow time for the data to
+    /// latch.
+    pub async fn refresh_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        let command: [u8; 2] = [NEOPIXEL_REGISTER_BASE, NeopixelRegister::Show as u8];
+        self.write(&command).await
+    }
+
+    /// Sets a pixel of the local framebuffer to a color without writing it to the
+    /// board. The change is sent by the next `flush` or `flush_write`. Returns
+    /// `NeotrellisError::InvalidKey` if `led_index` is not in 0-15.
+    pub fn set_pixel(&mut self, led_index: u8, color: impl Into<Color>) -> Result<(), NeotrellisError<I2C::Error>> {
+        if led_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+
+        self.framebuffer.set_pixel(led_index as usize, color.into());
+        Ok(())
+    }
+
+    /// Returns the color of a pixel in the local framebuffer, or `None` if `led_index`
+    /// is not in 0-15.
+    pub fn pixel(&self, led_index: u8) -> Option<Color> {
+        if led_index >= 16 { return None; }
+
+        Some(self.framebuffer.pixel(led_index as usize))
+    }
+
+    /// Sets every pixel of the local framebuffer to a color without writing it to
+    /// the board.
+    pub fn fill(&mut self, color: impl Into<Color>) {
+        let color = color.into();
+        for index in 0..16 { self.framebuffer.set_pixel(index, color); }
+    }
+
+    /// Writes the pixels changed since the last flush to the board and refreshes the LED
+    /// display. Nothing is sent if no pixels have changed.
+    pub async fn flush(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        if !self.framebuffer.is_dirty() { return Ok(()); }
+
+        self.flush_write().await?;
+        self.seesaw.delay.delay_us(300).await;
+        self.refresh_leds().await
+    }
+
+    /// The write part of the `flush` function. Sends the smallest byte range covering the
+    /// changed pixels in as few writes as the Seesaw allows, without refreshing the display.
+    pub async fn flush_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for (command, length) in self.framebuffer.dirty_chunks(self.correction) {
+            seesaw::write(&mut self.seesaw.i2c, self.seesaw.address, &command[..length]).await?;
+        }
+
+        self.framebuffer.mark_clean();
+        Ok(())
+    }
+
+    /// Sets the brightness every color is scaled by before it is sent to the board, from 0
+    /// (off) to 255 (full, the default). Colors are kept as they were set, so the brightness
+    /// can be turned back up without losing them. The pixels already on the board are
+    /// resent with the new brightness by the next `flush`.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        if brightness == self.correction.brightness { return; }
+
+        self.correction.brightness = brightness;
+        self.framebuffer.mark_all_dirty();
+    }
+
+    /// The brightness set with `set_brightness`.
+    pub fn brightness(&self) -> u8 {
+        self.correction.brightness
+    }
+
+    /// Sets the gamma correction lookup table applied to each color component after the
+    /// brightness, such as `GAMMA_2_8`, or `None` to send the components as they are (the
+    /// default). As with `set_brightness`, the next `flush` resends every pixel.
+    pub fn set_gamma(&mut self, table: Option<&'static [u8; 256]>) {
+        if table == self.correction.gamma { return; }
+
+        self.correction.gamma = table;
+        self.framebuffer.mark_all_dirty();
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        seesaw::write(&mut self.seesaw.i2c, self.seesaw.address, bytes).await
+    }
+
+    async fn read(&mut self, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        seesaw::read(&mut self.seesaw.i2c, self.seesaw.address, buffer).await
+    }
+
+    async fn read_byte(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        seesaw::read_byte(&mut self.seesaw.i2c, self.seesaw.address).await
+    }
+
+    async fn enable_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for command in crate::enable_leds_commands().iter() { self.write(command).await? }
+        Ok(())
+    }
+}
+
+impl <I2C, D> core::ops::Deref for Neotrellis<I2C, D> {
+    type Target = Seesaw<I2C, D>;
+
+    fn deref(&self) -> &Seesaw<I2C, D> {
+        &self.seesaw
+    }
+}
+
+impl <I2C, D> core::ops::DerefMut for Neotrellis<I2C, D> {
+    fn deref_mut(&mut self) -> &mut Seesaw<I2C, D> {
+        &mut self.seesaw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::sim::fixtures::async_initialized_bus;
+    use crate::sim::{NoDelay, SimulatedBus, Transaction};
+    use crate::{FirmwareVersion, NeotrellisEventType, Options, PinMode};
+
+    #[test]
+    fn initialize_sets_up_keypad_and_leds() {
+        let bus = async_initialized_bus();
+
+        let board = bus.board(DEFAULT_ADDRESS).unwrap();
+        assert_eq!(board.neopixel_pin(), Some(3));
+        assert_eq!(board.neopixel_speed(), Some(1));
+        assert_eq!(board.neopixel_buffer(), &[0; 48][..]);
+        assert_eq!(board.show_count(), 1);
+        assert!((0..16).all(|key| board.is_edge_enabled(key, 2) && board.is_edge_enabled(key, 3)));
+    }
+
+    #[test]
+    fn initialize_rejects_other_hardware() {
+        let mut bus = SimulatedBus::new(&[DEFAULT_ADDRESS]);
+        bus.board_mut(DEFAULT_ADDRESS).unwrap().set_hardware_id(0x87);
+
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        assert_eq!(block_on(nt.initialize()), Err(NeotrellisError::WrongHardwareId(0x87)));
+    }
+
+    #[test]
+    fn key_events_are_parsed() {
+        let mut bus = async_initialized_bus();
+        let board = bus.board_mut(DEFAULT_ADDRESS).unwrap();
+        board.press(6);
+        board.release(6);
+
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        let mut raw_events = [0; 16];
+        let events: std::vec::Vec<_> = block_on(nt.key_event_iterate(&mut raw_events))
+            .unwrap()
+            .map(|event| (event.key_index, event.event_type))
+            .collect();
+
+        assert_eq!(events, [(6, NeotrellisEventType::KeyPress), (6, NeotrellisEventType::KeyRelease)]);
+        assert_eq!(block_on(nt.key_event_iterate(&mut raw_events)).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn drain_reads_the_fifo_in_chunks() {
+        let mut bus = async_initialized_bus();
+        for key in 0..7 { bus.board_mut(DEFAULT_ADDRESS).unwrap().press(key); }
+
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        let mut keys = std::vec::Vec::new();
+        let mut raw_events = [0; 3];
+        let report = block_on(nt.key_event_drain(&mut raw_events, |event| keys.push(event.key_index))).unwrap();
+
+        assert_eq!(report, DrainReport { events: 7, dropped: 0, reads: 3 });
+        assert_eq!(keys, [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(block_on(nt.key_event_drain(&mut [], |_| {})), Err(NeotrellisError::BufferTooSmall));
+        assert!(bus.board(DEFAULT_ADDRESS).unwrap().fifo().is_empty());
+    }
+
+    #[test]
+    fn flush_writes_only_the_dirty_range() {
+        let mut bus = async_initialized_bus();
+
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        nt.set_pixel(2, (1, 2, 3)).unwrap();
+        nt.set_pixel(4, (4, 5, 6)).unwrap();
+        block_on(nt.flush()).unwrap();
+
+        // Nothing has changed, so the second flush sends nothing
+        nt.set_pixel(2, (1, 2, 3)).unwrap();
+        block_on(nt.flush()).unwrap();
+
+        assert_eq!(bus.transactions(), &[
+            Transaction::Write(DEFAULT_ADDRESS, std::vec![0x0E, 0x04, 0, 6, 2, 1, 3, 0, 0, 0, 5, 4, 6]),
+            Transaction::Write(DEFAULT_ADDRESS, std::vec![0x0E, 0x05])
+        ]);
+        assert_eq!(bus.board(DEFAULT_ADDRESS).unwrap().pixel(4), (4, 5, 6));
+    }
+
+    #[test]
+    fn status_and_gpio_are_read_through_the_seesaw_core() {
+        let mut bus = async_initialized_bus();
+        let board = bus.board_mut(DEFAULT_ADDRESS).unwrap();
+        board.set_version((3954 << 16) | (14 << 11) | (6 << 7) | 21);
+        board.set_temperature_raw(0xC019_8000);
+
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        assert_eq!(block_on(nt.firmware_version()), Ok(FirmwareVersion { product_code: 3954, year: 21, month: 6, day: 14 }));
+        assert_eq!(block_on(nt.temperature_c()), Ok(25.5));
+        assert!(block_on(nt.options()).unwrap().contains(Options::NEOPIXEL | Options::KEYPAD));
+
+        block_on(nt.pin_mode_bulk(0b0011, PinMode::Output)).unwrap();
+        block_on(nt.pin_mode(3, PinMode::InputPullUp)).unwrap();
+        block_on(nt.digital_write_bulk(0b0011, true)).unwrap();
+        block_on(nt.toggle_bulk(0b0001)).unwrap();
+        assert_eq!(block_on(nt.digital_read_bulk(0b1111)), Ok(0b1010));
+        assert_eq!(block_on(nt.digital_read(32)), Err(NeotrellisError::InvalidPin));
+
+        bus.board_mut(DEFAULT_ADDRESS).unwrap().set_gpio_input(3, false);
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        assert_eq!(block_on(nt.digital_read(3)), Ok(false));
+
+        block_on(nt.software_reset()).unwrap();
+        assert_eq!(bus.board(DEFAULT_ADDRESS).unwrap().reset_count(), 1);
+    }
+}
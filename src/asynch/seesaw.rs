@@ -0,0 +1,103 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::seesaw::{StatusRegister, MAX_WRITE_LENGTH, READ_DELAY_US, STATUS_REGISTER_BASE};
+use crate::NeotrellisError;
+
+/// Async counterpart of the `Seesaw` core: the register protocol, plus the status and GPIO
+/// modules that every board has.
+///
+/// The async `Neotrellis` dereferences to it, so its status and GPIO functions are
+/// available on the Neotrellis directly.
+pub struct Seesaw<I2C, D> {
+    pub(crate) i2c: I2C,
+    pub(crate) address: u8,
+
+    /// The delay provider used to await the Seesaw's timing requirements. It is public
+    /// so that it can also be used between the independent write and read functions.
+    pub delay: D
+}
+
+impl <I2C: I2c, D: DelayNs> Seesaw<I2C, D> {
+    /// Creates a Seesaw at `address`. Nothing is sent to the board.
+    pub fn new(i2c: I2C, delay: D, address: u8) -> Self {
+        Seesaw { i2c, address, delay }
+    }
+
+    /// Consumes the Seesaw and hands back the I2C bus and delay it was created with.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// The I2C address of the board.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Reads the hardware ID of the board.
+    pub async fn hardware_id(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        self.hardware_id_write().await?;
+        self.delay.delay_us(READ_DELAY_US).await;
+        self.hardware_id_read().await
+    }
+
+    /// The `write` part of the `hardware_id` function.
+    pub async fn hardware_id_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &[STATUS_REGISTER_BASE, StatusRegister::HardwareId as u8]).await
+    }
+
+    /// The `read` operation corresponding to the `hardware_id_write` function.
+    pub async fn hardware_id_read(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        read_byte(&mut self.i2c, self.address).await
+    }
+
+    /// Writes `data` to a register of a module. Returns `NeotrellisError::WriteTooLong`
+    /// if the write would be longer than `MAX_WRITE_LENGTH`.
+    pub async fn write_register(&mut self, base: u8, register: u8, data: &[u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        let mut command = [0; MAX_WRITE_LENGTH];
+        let length = data.len() + 2;
+        if length > MAX_WRITE_LENGTH { return Err(NeotrellisError::WriteTooLong); }
+
+        command[0] = base;
+        command[1] = register;
+        command[2..length].copy_from_slice(data);
+        write(&mut self.i2c, self.address, &command[..length]).await
+    }
+
+    /// Write/read operation that fills `buffer` with the data of a register.
+    pub async fn read_register(&mut self, base: u8, register: u8, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.select_register(base, register).await?;
+        self.delay.delay_us(READ_DELAY_US).await;
+        self.read_selected(buffer).await
+    }
+
+    /// The write part of the `read_register` function, which sets the register that the
+    /// next read returns.
+    pub async fn select_register(&mut self, base: u8, register: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &[base, register]).await
+    }
+
+    /// The read operation corresponding to `select_register`.
+    pub async fn read_selected(&mut self, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        read(&mut self.i2c, self.address, buffer).await
+    }
+}
+
+pub(crate) async fn write<I2C: I2c>(i2c: &mut I2C, address: u8, bytes: &[u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+    i2c.write(address, bytes).await.map_err(NeotrellisError::Bus)
+}
+
+pub(crate) async fn read<I2C: I2c>(i2c: &mut I2C, address: u8, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+    i2c.read(address, buffer).await.map_err(NeotrellisError::Bus)
+}
+
+pub(crate) async fn read_byte<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<u8, NeotrellisError<I2C::Error>> {
+    let mut read_buffer: [u8; 1] = [0; 1];
+    read(i2c, address, &mut read_buffer).await.map(|_| read_buffer[0])
+}
+
+pub(crate) async fn read_u32<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<u32, NeotrellisError<I2C::Error>> {
+    let mut bytes = [0; 4];
+    read(i2c, address, &mut bytes).await?;
+    Ok(u32::from_be_bytes(bytes))
+}
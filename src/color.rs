@@ -0,0 +1,225 @@
+/// An RGB color, accepted by every LED function of the drivers.
+///
+/// Anything that converts into a `Color` can be given instead, such as an `(r, g, b)`
+/// tuple or a `[r, g, b]` array. The drivers take care of the GRB byte order the
+/// NeoPixels use.
+///
+/// Example usage:
+/// ```ignore
+/// nt.set_led(0, Color::from_hex(0x6A0DAD)).unwrap();
+/// nt.set_led(1, Color::PURPLE.blend(Color::WHITE, 64)).unwrap();
+/// nt.set_led(2, (106, 13, 173)).unwrap();
+/// nt.fill(Color::from_hsv(172, 255, 64));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+    pub const CYAN: Color = Color::rgb(0, 255, 255);
+    pub const MAGENTA: Color = Color::rgb(255, 0, 255);
+    pub const ORANGE: Color = Color::rgb(255, 128, 0);
+    pub const PURPLE: Color = Color::rgb(106, 13, 173);
+    pub const PINK: Color = Color::rgb(255, 105, 180);
+
+    /// Creates a color from its red, green and blue components.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    /// Creates a color from a packed `0xRRGGBB` value, as used in CSS and design tools.
+    /// The top byte is ignored.
+    pub const fn from_hex(hex: u32) -> Self {
+        Color::rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    /// Packs the color into a `0xRRGGBB` value.
+    pub const fn to_hex(self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
+    }
+
+    /// Creates a color from a hue, saturation and value. The hue goes around the whole color
+    /// wheel from 0 to 255, starting and ending at red, with green at 86 and blue at 172.
+    pub fn from_hsv(hue: u8, saturation: u8, value: u8) -> Self {
+        if saturation == 0 { return Color::rgb(value, value, value); }
+
+        // Six sectors of 43 hue steps, each blending two of the primaries
+        let sector = hue / 43;
+        let position = (hue - sector * 43) as u32 * 6;
+        let (s, v) = (saturation as u32, value as u32);
+
+        let p = (v * (255 - s) / 255) as u8;
+        let q = (v * (255 - s * position / 255) / 255) as u8;
+        let t = (v * (255 - s * (255 - position) / 255) / 255) as u8;
+
+        match sector {
+            0 => Color::rgb(value, t, p),
+            1 => Color::rgb(q, value, p),
+            2 => Color::rgb(p, value, t),
+            3 => Color::rgb(p, q, value),
+            4 => Color::rgb(t, p, value),
+            _ => Color::rgb(value, p, q)
+        }
+    }
+
+    /// Converts the color to a `(hue, saturation, value)` on the same scales as `from_hsv`.
+    pub fn to_hsv(self) -> (u8, u8, u8) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = (max - min) as i32;
+        if delta == 0 { return (0, 0, max); }
+
+        let saturation = (255 * delta / max as i32) as u8;
+        let (r, g, b) = (self.r as i32, self.g as i32, self.b as i32);
+        let hue = if max == self.r {
+            43 * (g - b) / delta
+        } else if max == self.g {
+            86 + 43 * (b - r) / delta
+        } else {
+            172 + 43 * (r - g) / delta
+        };
+
+        (hue.rem_euclid(256) as u8, saturation, max)
+    }
+
+    /// Mixes in `other` by `amount`, from 0 for this color to 255 for `other`.
+    pub fn blend(self, other: Color, amount: u8) -> Color {
+        let mix = |from: u8, to: u8| {
+            let (from, to, amount) = (from as u32, to as u32, amount as u32);
+            ((from * (255 - amount) + to * amount + 127) / 255) as u8
+        };
+
+        Color::rgb(mix(self.r, other.r), mix(self.g, other.g), mix(self.b, other.b))
+    }
+
+    /// Scales every component by `factor / 255`, e.g. to dim the color.
+    pub fn scale(self, factor: u8) -> Color {
+        Color::rgb(scale(self.r, factor), scale(self.g, factor), scale(self.b, factor))
+    }
+}
+
+/// A gamma correction lookup table for a gamma of 2.8, which suits the NeoTrellis LEDs.
+/// Used with `Neotrellis::set_gamma`.
+pub static GAMMA_2_8: [u8; 256] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   1,   1,   1,   1,
+      1,   1,   1,   1,   1,   1,   1,   1,   1,   2,   2,   2,   2,   2,   2,   2,
+      2,   3,   3,   3,   3,   3,   3,   3,   4,   4,   4,   4,   4,   5,   5,   5,
+      5,   6,   6,   6,   6,   7,   7,   7,   7,   8,   8,   8,   9,   9,   9,  10,
+     10,  10,  11,  11,  11,  12,  12,  13,  13,  13,  14,  14,  15,  15,  16,  16,
+     17,  17,  18,  18,  19,  19,  20,  20,  21,  21,  22,  22,  23,  24,  24,  25,
+     25,  26,  27,  27,  28,  29,  29,  30,  31,  32,  32,  33,  34,  35,  35,  36,
+     37,  38,  39,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  50,
+     51,  52,  54,  55,  56,  57,  58,  59,  60,  61,  62,  63,  64,  66,  67,  68,
+     69,  70,  72,  73,  74,  75,  77,  78,  79,  81,  82,  83,  85,  86,  87,  89,
+     90,  92,  93,  95,  96,  98,  99, 101, 102, 104, 105, 107, 109, 110, 112, 114,
+    115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142,
+    144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213,
+    215, 218, 220, 223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255
+];
+
+/// The brightness and gamma correction the drivers apply to colors as they are sent to the
+/// board. Colors are scaled by the brightness first, so that the gamma table makes each
+/// brightness step look even.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ColorCorrection {
+    pub(crate) brightness: u8,
+    pub(crate) gamma: Option<&'static [u8; 256]>
+}
+
+impl ColorCorrection {
+    /// Full brightness and no gamma correction, which sends colors unchanged.
+    pub(crate) const NONE: ColorCorrection = ColorCorrection { brightness: 255, gamma: None };
+
+    pub(crate) fn apply(&self, color: Color) -> Color {
+        Color::rgb(self.apply_channel(color.r), self.apply_channel(color.g), self.apply_channel(color.b))
+    }
+
+    /// Corrects a single color component; the correction is the same for red, green and blue.
+    pub(crate) fn apply_channel(&self, value: u8) -> u8 {
+        let scaled = scale(value, self.brightness);
+        match self.gamma {
+            Some(table) => table[scaled as usize],
+            None => scaled
+        }
+    }
+}
+
+fn scale(value: u8, factor: u8) -> u8 {
+    ((value as u32 * factor as u32 + 127) / 255) as u8
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Color::rgb(r, g, b)
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Color::rgb(r, g, b)
+    }
+}
+
+impl From<Color> for (u8, u8, u8) {
+    fn from(color: Color) -> Self {
+        (color.r, color.g, color.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(Color::from_hex(0x6A0DAD), Color::PURPLE);
+        assert_eq!(Color::PURPLE.to_hex(), 0x6A0DAD);
+        assert_eq!(Color::from_hex(0xFF00_FF00), Color::GREEN);
+    }
+
+    #[test]
+    fn hsv_conversions() {
+        assert_eq!(Color::from_hsv(0, 255, 255), Color::RED);
+        assert_eq!(Color::from_hsv(172, 255, 255), Color::BLUE);
+        assert_eq!(Color::from_hsv(99, 0, 40), Color::rgb(40, 40, 40));
+        assert_eq!(Color::from_hsv(42, 255, 0), Color::BLACK);
+
+        assert_eq!(Color::RED.to_hsv(), (0, 255, 255));
+        assert_eq!(Color::GREEN.to_hsv(), (86, 255, 255));
+        assert_eq!(Color::BLUE.to_hsv(), (172, 255, 255));
+        assert_eq!(Color::MAGENTA.to_hsv(), (213, 255, 255));
+        assert_eq!(Color::from_hsv(Color::GREEN.to_hsv().0, 255, 255), Color::GREEN);
+        assert_eq!(Color::rgb(0, 0, 0).to_hsv(), (0, 0, 0));
+    }
+
+    #[test]
+    fn blend_and_scale() {
+        assert_eq!(Color::BLACK.blend(Color::WHITE, 0), Color::BLACK);
+        assert_eq!(Color::BLACK.blend(Color::WHITE, 255), Color::WHITE);
+        assert_eq!(Color::RED.blend(Color::BLUE, 128), Color::rgb(127, 0, 128));
+        assert_eq!(Color::WHITE.scale(64), Color::rgb(64, 64, 64));
+    }
+
+    #[test]
+    fn correction_scales_before_gamma() {
+        assert_eq!(ColorCorrection::NONE.apply(Color::PURPLE), Color::PURPLE);
+
+        let dimmed = ColorCorrection { brightness: 128, gamma: None };
+        assert_eq!(dimmed.apply(Color::rgb(255, 100, 0)), Color::rgb(128, 50, 0));
+
+        let corrected = ColorCorrection { brightness: 128, gamma: Some(&GAMMA_2_8) };
+        assert_eq!(corrected.apply(Color::rgb(255, 100, 0)), Color::rgb(37, 3, 0));
+        assert_eq!(GAMMA_2_8[255], 255);
+    }
+}
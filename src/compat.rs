@@ -0,0 +1,99 @@
+//! Adapters for HALs that only provide the embedded-hal 0.2 traits.
+//!
+//! The drivers in this crate are built on the embedded-hal 1.0 `I2c` and `DelayNs` traits.
+//! HALs that still implement the 0.2 `blocking::i2c::{Write, Read}` and
+//! `blocking::delay::DelayUs<u32>` traits can be used by wrapping their bus in `Eh02I2c`
+//! and their delay in `Eh02Delay`, which requires the `embedded-hal-02` feature:
+//! ```ignore
+//! let mut nt = Neotrellis::new(Eh02I2c::new(twim), Eh02Delay::new(delay), None);
+//! ```
+
+use core::fmt::Debug;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{Error, ErrorKind, ErrorType, I2c, Operation};
+use embedded_hal_02::blocking::delay::DelayUs;
+use embedded_hal_02::blocking::i2c::{Read, Write};
+
+/// Wraps an embedded-hal 0.2 I2C bus so that it implements the embedded-hal 1.0 `I2c` trait.
+///
+/// The 0.2 traits have no way to chain operations without a stop condition, so each
+/// operation of a transaction is sent as its own read or write.
+pub struct Eh02I2c<I2C> {
+    i2c: I2C
+}
+
+/// Error type of `Eh02I2c`, wrapping the error of the embedded-hal 0.2 bus.
+#[derive(Debug)]
+pub struct Eh02Error<E>(pub E);
+
+impl <E: Debug> Error for Eh02Error<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl <I2C> Eh02I2c<I2C> {
+    /// Wraps the embedded-hal 0.2 `i2c` bus.
+    pub fn new(i2c: I2C) -> Self {
+        Eh02I2c { i2c }
+    }
+
+    /// Consumes the adapter and hands back the wrapped bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl <I2C, E> ErrorType for Eh02I2c<I2C> where I2C: Write<Error = E> + Read<Error = E>, E: Debug {
+    type Error = Eh02Error<E>;
+}
+
+impl <I2C, E> I2c for Eh02I2c<I2C> where I2C: Write<Error = E> + Read<Error = E>, E: Debug {
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.read(address, read).map_err(Eh02Error)
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.i2c.write(address, write).map_err(Eh02Error)
+    }
+
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                Operation::Read(buffer) => self.read(address, buffer)?,
+                Operation::Write(bytes) => self.write(address, bytes)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps an embedded-hal 0.2 `DelayUs<u32>` implementation so that it implements the
+/// embedded-hal 1.0 `DelayNs` trait.
+pub struct Eh02Delay<D> {
+    delay: D
+}
+
+impl <D> Eh02Delay<D> {
+    /// Wraps the embedded-hal 0.2 `delay` provider.
+    pub fn new(delay: D) -> Self {
+        Eh02Delay { delay }
+    }
+
+    /// Consumes the adapter and hands back the wrapped delay provider.
+    pub fn release(self) -> D {
+        self.delay
+    }
+}
+
+impl <D: DelayUs<u32>> DelayNs for Eh02Delay<D> {
+    fn delay_ns(&mut self, ns: u32) {
+        // Round up so that a delay is never shorter than requested
+        self.delay.delay_us(ns.div_ceil(1_000))
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us)
+    }
+}
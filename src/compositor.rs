@@ -0,0 +1,227 @@
+use crate::animation::Leds;
+use crate::Color;
+
+/// How a layer is combined with the layers below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The layer is painted over the layers below it.
+    Over,
+
+    /// The layer's components are added to the layers below it, up to full brightness.
+    Add,
+
+    /// The layer's components scale the layers below it, so white keeps them and black
+    /// turns them off.
+    Multiply,
+
+    /// Each component is the brighter of the layer's and the layers below it.
+    Max
+}
+
+impl BlendMode {
+    fn apply(self, below: Color, layer: Color) -> Color {
+        let combine = |f: fn(u8, u8) -> u8| Color::rgb(f(below.r, layer.r), f(below.g, layer.g), f(below.b, layer.b));
+        match self {
+            BlendMode::Over => layer,
+            BlendMode::Add => combine(u8::saturating_add),
+            BlendMode::Multiply => combine(|below, layer| ((below as u32 * layer as u32 + 127) / 255) as u8),
+            BlendMode::Max => combine(u8::max)
+        }
+    }
+}
+
+/// A layer of `N` pixels, each with its own alpha, in a `Compositor`.
+///
+/// A new layer is fully transparent. Setting a pixel makes it opaque unless an alpha is
+/// given, and indices past the end of the layer are ignored. Effects from the `animation`
+/// module can render into a layer, as it implements `Leds`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layer<const N: usize = 16> {
+    /// Hidden layers are skipped when compositing.
+    pub visible: bool,
+
+    /// The opacity of the whole layer, multiplied with the alpha of each pixel.
+    pub opacity: u8,
+
+    /// How the layer is combined with the layers below it.
+    pub mode: BlendMode,
+
+    pixels: [Color; N],
+    alpha: [u8; N]
+}
+
+impl <const N: usize> Layer<N> {
+    /// Creates a visible, fully transparent layer painted `Over` the layers below it.
+    pub fn new() -> Self {
+        Layer { visible: true, opacity: 255, mode: BlendMode::Over, pixels: [Color::BLACK; N], alpha: [0; N] }
+    }
+
+    /// Sets a pixel to an opaque color.
+    pub fn set(&mut self, index: usize, color: impl Into<Color>) {
+        self.set_with_alpha(index, color, 255);
+    }
+
+    /// Sets a pixel to a color with an alpha, from 0 (transparent) to 255 (opaque).
+    pub fn set_with_alpha(&mut self, index: usize, color: impl Into<Color>, alpha: u8) {
+        if index >= N { return; }
+        self.pixels[index] = color.into();
+        self.alpha[index] = alpha;
+    }
+
+    /// Makes a pixel transparent.
+    pub fn clear(&mut self, index: usize) {
+        self.set_with_alpha(index, Color::BLACK, 0);
+    }
+
+    /// Makes every pixel transparent.
+    pub fn clear_all(&mut self) {
+        self.alpha = [0; N];
+    }
+
+    /// Sets every pixel to an opaque color.
+    pub fn fill(&mut self, color: impl Into<Color>) {
+        self.pixels = [color.into(); N];
+        self.alpha = [255; N];
+    }
+
+    /// Returns the color and alpha of a pixel, or `None` if `index` is past the end of the layer.
+    pub fn pixel(&self, index: usize) -> Option<(Color, u8)> {
+        if index >= N { return None; }
+        Some((self.pixels[index], self.alpha[index]))
+    }
+}
+
+impl <const N: usize> Default for Layer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <const N: usize> Leds for Layer<N> {
+    fn led_count(&self) -> usize {
+        N
+    }
+
+    fn color(&self, index: usize) -> Color {
+        self.pixel(index).map_or(Color::BLACK, |(color, _)| color)
+    }
+
+    fn set_color(&mut self, index: usize, color: Color) {
+        self.set(index, color);
+    }
+}
+
+/// Stacks `LAYERS` layers of `N` pixels and combines them into the colors sent to the LEDs.
+///
+/// `layers[0]` is the bottom layer. Compositing starts from the `background` color and
+/// combines each visible layer on top in turn, using the layer's `mode` mixed in by the
+/// alpha of each pixel and the opacity of the layer.
+///
+/// Example usage: a pattern, a playhead and a blinking cursor:
+/// ```ignore
+/// let mut ui: Compositor<3> = Compositor::new();
+/// Rainbow::new(4_000).tick(now, &mut ui.layers[0]);
+/// ui.layers[0].opacity = 64;
+/// ui.layers[1].set(playhead, Color::WHITE);
+/// ui.layers[2].set(cursor, Color::RED);
+/// ui.layers[2].visible = now % 500 < 250;
+///
+/// ui.render(&mut nt);
+/// nt.flush().unwrap();
+/// ```
+pub struct Compositor<const LAYERS: usize, const N: usize = 16> {
+    /// The layers, from the bottom up.
+    pub layers: [Layer<N>; LAYERS],
+
+    /// The color below every layer.
+    pub background: Color
+}
+
+impl <const LAYERS: usize, const N: usize> Compositor<LAYERS, N> {
+    /// Creates a compositor of transparent layers on a black background.
+    pub fn new() -> Self {
+        Compositor { layers: [Layer::new(); LAYERS], background: Color::BLACK }
+    }
+
+    /// Combines the layers into the color of every pixel.
+    pub fn composite(&self) -> [Color; N] {
+        let mut colors = [self.background; N];
+        for layer in self.layers.iter().filter(|layer| layer.visible && layer.opacity > 0) {
+            for (index, color) in colors.iter_mut().enumerate() {
+                let alpha = ((layer.alpha[index] as u32 * layer.opacity as u32 + 127) / 255) as u8;
+                if alpha == 0 { continue; }
+
+                *color = color.blend(layer.mode.apply(*color, layer.pixels[index]), alpha);
+            }
+        }
+
+        colors
+    }
+
+    /// Composites the layers and writes the result into `leds`, such as the framebuffer of a
+    /// `Neotrellis`, which then only needs a `flush` to show it.
+    pub fn render(&self, leds: &mut dyn Leds) {
+        for (index, color) in self.composite().iter().enumerate() {
+            leds.set_color(index, *color);
+        }
+    }
+}
+
+impl <const LAYERS: usize, const N: usize> Default for Compositor<LAYERS, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+
+    #[test]
+    fn blend_modes_combine_with_the_layers_below() {
+        let below = Color::rgb(200, 100, 0);
+        let layer = Color::rgb(100, 255, 50);
+
+        assert_eq!(BlendMode::Over.apply(below, layer), layer);
+        assert_eq!(BlendMode::Add.apply(below, layer), Color::rgb(255, 255, 50));
+        assert_eq!(BlendMode::Multiply.apply(below, layer), Color::rgb(78, 100, 0));
+        assert_eq!(BlendMode::Max.apply(below, layer), Color::rgb(200, 255, 50));
+    }
+
+    #[test]
+    fn layers_are_stacked_with_alpha_opacity_and_visibility() {
+        let mut ui: Compositor<3, 4> = Compositor::new();
+        ui.background = Color::rgb(0, 0, 40);
+        ui.layers[0].fill(Color::RED);
+        ui.layers[0].clear(3);
+        ui.layers[1].set_with_alpha(1, Color::WHITE, 128);
+        ui.layers[1].mode = BlendMode::Add;
+        ui.layers[2].fill(Color::GREEN);
+        ui.layers[2].visible = false;
+
+        assert_eq!(ui.composite(), [Color::RED, Color::rgb(255, 128, 128), Color::RED, Color::rgb(0, 0, 40)]);
+
+        ui.layers[0].opacity = 0;
+        ui.layers[2].visible = true;
+        ui.layers[2].opacity = 128;
+        assert_eq!(ui.composite()[0], Color::rgb(0, 128, 20));
+    }
+
+    #[test]
+    fn render_writes_the_composite_to_the_framebuffer() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        let mut ui: Compositor<2> = Compositor::new();
+        ui.layers[0].fill(Color::BLUE);
+        ui.layers[1].set(7, Color::YELLOW);
+        ui.render(&mut nt);
+        nt.flush().unwrap();
+
+        let bus = bus.borrow();
+        let board = bus.board(0x2E).unwrap();
+        assert_eq!(board.pixel(0), (0, 0, 255));
+        assert_eq!(board.pixel(7), (255, 255, 0));
+    }
+}
@@ -0,0 +1,25 @@
+//! Adapters for providing the delays that the Seesaw requires between writes and reads.
+//!
+//! The drivers accept any embedded-hal 1.0 `DelayNs` implementation, which most HALs
+//! provide for their timers. `FnDelay` adapts a plain function or closure instead.
+
+use embedded_hal::delay::DelayNs;
+
+/// Wraps a function or closure that delays for a given number of microseconds so that
+/// it can be used as a `DelayNs` implementation.
+///
+/// ```ignore
+/// let mut nt = Neotrellis::new(i2c, FnDelay(|us| asm::delay(64 * us)), None);
+/// ```
+pub struct FnDelay<F>(pub F);
+
+impl <F: FnMut(u32)> DelayNs for FnDelay<F> {
+    fn delay_ns(&mut self, ns: u32) {
+        // Round up so that a delay is never shorter than requested
+        (self.0)(ns.div_ceil(1_000))
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        (self.0)(us)
+    }
+}
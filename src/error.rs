@@ -0,0 +1,43 @@
+use core::fmt;
+
+/// Error type returned by the Neotrellis drivers.
+///
+/// `E` is the error type of the underlying I2C bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeotrellisError<E> {
+    /// The I2C bus reported an error.
+    Bus(E),
+
+    /// The board answered with a hardware ID other than the 0x55 of a NeoTrellis. This
+    /// usually means another Seesaw product is at the address or the board is unplugged.
+    WrongHardwareId(u8),
+
+    /// The buffer passed in is too small to hold the data the board has to give.
+    BufferTooSmall,
+
+    /// The data to write does not fit in a single Seesaw write of `MAX_WRITE_LENGTH` bytes.
+    WriteTooLong,
+
+    /// The key or LED index is outside of the board or grid.
+    InvalidKey,
+
+    /// The Seesaw GPIO pin number is outside of 0-31.
+    InvalidPin,
+
+    /// The interrupt pin could not be read.
+    Pin
+}
+
+impl <E: fmt::Debug> fmt::Display for NeotrellisError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NeotrellisError::Bus(error) => write!(f, "I2C bus error: {:?}", error),
+            NeotrellisError::WrongHardwareId(id) => write!(f, "unexpected hardware ID 0x{:02X}", id),
+            NeotrellisError::BufferTooSmall => write!(f, "buffer too small"),
+            NeotrellisError::WriteTooLong => write!(f, "write too long"),
+            NeotrellisError::InvalidKey => write!(f, "invalid key index"),
+            NeotrellisError::InvalidPin => write!(f, "invalid GPIO pin"),
+            NeotrellisError::Pin => write!(f, "interrupt pin error")
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::seesaw::{NEOPIXEL_REGISTER_BASE, NeopixelRegister};
+use crate::color::{Color, ColorCorrection};
+
+/// The number of pixel bytes sent in each NeoPixel buffer write. The Seesaw cannot take
+/// the whole buffer in one write, so larger updates are split into chunks of this size.
+pub(crate) const CHUNK_SIZE: usize = 8 * 3;
+
+/// An in-RAM copy of the 16 pixels of a Neotrellis, stored in the GRB byte order used by
+/// the Seesaw's NeoPixel buffer. The pixels are kept as they were set; the brightness and
+/// gamma correction are applied as they are written out.
+///
+/// Changes are tracked as a single dirty byte range so that only the bytes that differ
+/// from the board need to be written when the framebuffer is flushed.
+pub(crate) struct Framebuffer {
+    bytes: [u8; 16 * 3],
+    dirty_start: usize,
+    dirty_end: usize
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with every pixel off, matching a freshly cleared board.
+    pub(crate) fn new() -> Self {
+        Framebuffer { bytes: [0; 16 * 3], dirty_start: 0, dirty_end: 0 }
+    }
+
+    pub(crate) fn pixel(&self, index: usize) -> Color {
+        let offset = index * 3;
+        Color::rgb(self.bytes[offset + 1], self.bytes[offset], self.bytes[offset + 2])
+    }
+
+    /// Changes a pixel and marks it dirty if its value differs from the current one.
+    pub(crate) fn set_pixel(&mut self, index: usize, color: Color) {
+        let offset = index * 3;
+        let grb = [color.g, color.r, color.b];
+        if self.bytes[offset..offset + 3] == grb { return; }
+
+        self.bytes[offset..offset + 3].copy_from_slice(&grb);
+        if self.is_dirty() {
+            self.dirty_start = self.dirty_start.min(offset);
+            self.dirty_end = self.dirty_end.max(offset + 3);
+        } else {
+            self.dirty_start = offset;
+            self.dirty_end = offset + 3;
+        }
+    }
+
+    /// Records a pixel that has already been written to the board without marking it dirty.
+    pub(crate) fn store_pixel(&mut self, index: usize, color: Color) {
+        let offset = index * 3;
+        self.bytes[offset..offset + 3].copy_from_slice(&[color.g, color.r, color.b]);
+    }
+
+    /// Records that the whole board has been cleared.
+    pub(crate) fn clear(&mut self) {
+        *self = Framebuffer::new();
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty_start != self.dirty_end
+    }
+
+    /// Marks every pixel dirty, e.g. when the correction applied to all of them has changed.
+    pub(crate) fn mark_all_dirty(&mut self) {
+        self.dirty_start = 0;
+        self.dirty_end = self.bytes.len();
+    }
+
+    pub(crate) fn mark_clean(&mut self) {
+        self.dirty_start = 0;
+        self.dirty_end = 0;
+    }
+
+    /// Returns an iterator over the NeoPixel buffer write commands needed to send the
+    /// dirty byte range to the board with `correction` applied.
+    pub(crate) fn dirty_chunks(&self, correction: ColorCorrection) -> DirtyChunks<'_> {
+        DirtyChunks { framebuffer: self, correction, offset: self.dirty_start }
+    }
+}
+
+pub(crate) struct DirtyChunks<'a> {
+    framebuffer: &'a Framebuffer,
+    correction: ColorCorrection,
+    offset: usize
+}
+
+impl <'a> Iterator for DirtyChunks<'a> {
+    /// A command buffer and the number of bytes of it to write.
+    type Item = ([u8; CHUNK_SIZE + 4], usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.framebuffer.dirty_end { return None; }
+
+        let length = (self.framebuffer.dirty_end - self.offset).min(CHUNK_SIZE);
+        let mut command = [0; CHUNK_SIZE + 4];
+        command[0] = NEOPIXEL_REGISTER_BASE;
+        command[1] = NeopixelRegister::Buffer as u8;
+        command[2] = 0; // Offset high
+        command[3] = self.offset as u8; // Offset low
+        let pixels = &self.framebuffer.bytes[self.offset..self.offset + length];
+        for (byte, &value) in command[4..4 + length].iter_mut().zip(pixels) {
+            *byte = self.correction.apply_channel(value);
+        }
+
+        self.offset += length;
+        Some((command, length + 4))
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{NeotrellisEvent, NeotrellisEventType};
+
+/// Timing thresholds used by the `GestureDetector`, in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// How long a key must be held to generate a `Gesture::LongPress`.
+    pub long_press_ms: u32,
+
+    /// The longest time between two presses of a key that generates a `Gesture::DoubleTap`.
+    pub double_tap_ms: u32,
+
+    /// The longest time between the presses of keys that are held together for them to
+    /// generate a `Gesture::Chord`.
+    pub chord_window_ms: u32,
+
+    /// How long a key must be held before it starts generating `Gesture::Repeat`s.
+    pub repeat_delay_ms: u32,
+
+    /// The time between `Gesture::Repeat`s while a key is held, or 0 to disable repeats.
+    pub repeat_interval_ms: u32
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            long_press_ms: 500,
+            double_tap_ms: 300,
+            chord_window_ms: 50,
+            repeat_delay_ms: 500,
+            repeat_interval_ms: 100
+        }
+    }
+}
+
+/// A gesture recognized by the `GestureDetector`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// The key has been held for `long_press_ms`.
+    LongPress(usize),
+
+    /// The key was pressed a second time within `double_tap_ms` of a short press.
+    DoubleTap(usize),
+
+    /// The keys in the mask, where bit n is key n, were pressed together within
+    /// `chord_window_ms` of each other and are held.
+    Chord(u16),
+
+    /// The key is still held; generated every `repeat_interval_ms` after `repeat_delay_ms`.
+    Repeat(usize)
+}
+
+/// Recognizes long presses, double taps, chords and key repeats from the keypad events.
+///
+/// Events are fed in with `update` and time is advanced with `tick`, both with a
+/// free-running millisecond timestamp. Keys that are part of a chord do not generate
+/// other gestures until they are released.
+///
+/// Example usage:
+/// ```ignore
+/// let mut gestures = GestureDetector::new(GestureConfig::default());
+///
+/// loop {
+///     let now = timer.now_ms();
+///     for event in nt.key_event_iterate(&mut raw_events).unwrap() {
+///         if let Some(gesture) = gestures.update(&event, now) { handle(gesture); }
+///     }
+///
+///     while let Some(gesture) = gestures.tick(now) { handle(gesture); }
+/// }
+/// ```
+pub struct GestureDetector {
+    config: GestureConfig,
+    pressed_at: [Option<u32>; 16],
+    tapped_at: [Option<u32>; 16],
+    next_repeat_at: [u32; 16],
+    long_pressed: u16,
+    chorded: u16
+}
+
+impl GestureDetector {
+    /// Creates a detector with every key released.
+    pub fn new(config: GestureConfig) -> Self {
+        GestureDetector {
+            config,
+            pressed_at: [None; 16],
+            tapped_at: [None; 16],
+            next_repeat_at: [0; 16],
+            long_pressed: 0,
+            chorded: 0
+        }
+    }
+
+    /// The thresholds the detector was created with.
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    /// Feeds a keypad event that was read at `now_ms`. Presses can complete a double tap
+    /// or a chord, which is returned. Level events are ignored.
+    pub fn update(&mut self, event: &NeotrellisEvent, now_ms: u32) -> Option<Gesture> {
+        let key_index = event.key_index;
+        if key_index >= 16 { return None; }
+        let bit = 1 << key_index;
+
+        match event.event_type {
+            NeotrellisEventType::KeyPress => {
+                if self.pressed_at[key_index].is_some() { return None; }
+
+                self.pressed_at[key_index] = Some(now_ms);
+                self.next_repeat_at[key_index] = now_ms.wrapping_add(self.config.repeat_delay_ms);
+
+                let partners = self.chord_partners(key_index, now_ms);
+                if partners != 0 {
+                    self.chorded |= partners | bit;
+                    for (index, tapped_at) in self.tapped_at.iter_mut().enumerate() {
+                        if self.chorded & (1 << index) != 0 { *tapped_at = None; }
+                    }
+
+                    return Some(Gesture::Chord(self.chorded));
+                }
+
+                match self.tapped_at[key_index].take() {
+                    Some(tapped_at) if now_ms.wrapping_sub(tapped_at) <= self.config.double_tap_ms => {
+                        Some(Gesture::DoubleTap(key_index))
+                    },
+                    _ => {
+                        self.tapped_at[key_index] = Some(now_ms);
+                        None
+                    }
+                }
+            },
+            NeotrellisEventType::KeyRelease => {
+                // Only short, solo presses count towards a double tap
+                if (self.long_pressed | self.chorded) & bit != 0 { self.tapped_at[key_index] = None; }
+
+                self.pressed_at[key_index] = None;
+                self.long_pressed &= !bit;
+                self.chorded &= !bit;
+                None
+            },
+            _ => None
+        }
+    }
+
+    /// Advances time to `now_ms` and returns the next long press or repeat that is due.
+    /// Should be called repeatedly until it returns `None`.
+    pub fn tick(&mut self, now_ms: u32) -> Option<Gesture> {
+        for key_index in 0..16 {
+            let bit = 1 << key_index;
+            if self.chorded & bit != 0 { continue; }
+            let pressed_at = match self.pressed_at[key_index] {
+                Some(pressed_at) => pressed_at,
+                None => continue
+            };
+
+            if self.long_pressed & bit == 0 && now_ms.wrapping_sub(pressed_at) >= self.config.long_press_ms {
+                self.long_pressed |= bit;
+                self.tapped_at[key_index] = None;
+                return Some(Gesture::LongPress(key_index));
+            }
+
+            let next_repeat_at = self.next_repeat_at[key_index];
+            if self.config.repeat_interval_ms > 0 && is_due(now_ms, next_repeat_at) {
+                self.next_repeat_at[key_index] = next_repeat_at.wrapping_add(self.config.repeat_interval_ms);
+                return Some(Gesture::Repeat(key_index));
+            }
+        }
+
+        None
+    }
+
+    /// Mask of the other held keys that were pressed within the chord window, or are
+    /// already part of a chord.
+    fn chord_partners(&self, key_index: usize, now_ms: u32) -> u16 {
+        let mut partners = 0;
+        for (index, pressed_at) in self.pressed_at.iter().enumerate() {
+            if index == key_index { continue; }
+
+            if let Some(pressed_at) = pressed_at {
+                let recent = now_ms.wrapping_sub(*pressed_at) <= self.config.chord_window_ms;
+                if recent || self.chorded & (1 << index) != 0 { partners |= 1 << index; }
+            }
+        }
+
+        partners
+    }
+}
+
+/// Returns true if `deadline` is at or before `now`, allowing for the timestamps wrapping.
+fn is_due(now: u32, deadline: u32) -> bool {
+    now.wrapping_sub(deadline) < u32::MAX / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{press, release};
+
+    #[test]
+    fn long_press_then_repeats() {
+        let mut gestures = GestureDetector::new(GestureConfig::default());
+
+        assert_eq!(gestures.update(&press(4), 1_000), None);
+        assert_eq!(gestures.tick(1_499), None);
+        assert_eq!(gestures.tick(1_500), Some(Gesture::LongPress(4)));
+        assert_eq!(gestures.tick(1_500), Some(Gesture::Repeat(4)));
+        assert_eq!(gestures.tick(1_500), None);
+        assert_eq!(gestures.tick(1_600), Some(Gesture::Repeat(4)));
+
+        gestures.update(&release(4), 1_650);
+        assert_eq!(gestures.tick(2_000), None);
+    }
+
+    #[test]
+    fn double_tap_needs_two_short_presses() {
+        let mut gestures = GestureDetector::new(GestureConfig::default());
+
+        gestures.update(&press(2), 0);
+        gestures.update(&release(2), 80);
+        assert_eq!(gestures.update(&press(2), 200), Some(Gesture::DoubleTap(2)));
+        gestures.update(&release(2), 250);
+
+        // Too slow
+        gestures.update(&press(2), 1_000);
+        gestures.update(&release(2), 1_050);
+        assert_eq!(gestures.update(&press(2), 1_400), None);
+        gestures.update(&release(2), 1_450);
+
+        // A long press does not count as the first tap
+        gestures.update(&press(2), 3_000);
+        assert_eq!(gestures.tick(3_500), Some(Gesture::LongPress(2)));
+        gestures.update(&release(2), 3_600);
+        assert_eq!(gestures.update(&press(2), 3_700), None);
+    }
+
+    #[test]
+    fn chords_suppress_other_gestures() {
+        let mut gestures = GestureDetector::new(GestureConfig::default());
+
+        assert_eq!(gestures.update(&press(0), 0), None);
+        assert_eq!(gestures.update(&press(5), 30), Some(Gesture::Chord(0b10_0001)));
+        assert_eq!(gestures.update(&press(6), 500), Some(Gesture::Chord(0b110_0001)));
+        assert_eq!(gestures.tick(2_000), None);
+
+        gestures.update(&release(0), 2_100);
+        gestures.update(&release(5), 2_100);
+        gestures.update(&release(6), 2_100);
+        assert_eq!(gestures.update(&press(0), 2_200), None);
+    }
+}
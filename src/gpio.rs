@@ -0,0 +1,326 @@
+use core::cell::RefCell;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, ErrorType, InputPin, OutputPin};
+use embedded_hal::i2c::I2c;
+
+use crate::seesaw::{read_u32, write, GpioRegister, Seesaw, SeesawDevice, GPIO_REGISTER_BASE, READ_DELAY_US};
+use crate::NeotrellisError;
+#[cfg(feature = "async")]
+use crate::asynch::seesaw as asynch;
+
+/// How a Seesaw GPIO pin is configured, used with `Seesaw::pin_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinMode {
+    /// The pin drives its output level.
+    Output,
+
+    /// The pin floats and reads the level it is driven to.
+    Input,
+
+    /// The pin reads high unless it is pulled low, e.g. by a button to ground.
+    InputPullUp,
+
+    /// The pin reads low unless it is pulled high.
+    InputPullDown
+}
+
+impl <I2C: I2c, D: DelayNs> Seesaw<I2C, D> {
+    /// Configures a Seesaw GPIO pin. Returns `NeotrellisError::InvalidPin` if `pin` is not in 0-31.
+    pub fn pin_mode(&mut self, pin: u8, mode: PinMode) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.pin_mode_bulk(pin_mask(pin)?, mode)
+    }
+
+    /// Configures every Seesaw GPIO pin in `mask`, where bit n is pin n.
+    pub fn pin_mode_bulk(&mut self, mask: u32, mode: PinMode) -> Result<(), NeotrellisError<I2C::Error>> {
+        for command in pin_mode_commands(mask, mode).iter().flatten() {
+            write(&mut self.i2c, self.address, command)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the output level of a Seesaw GPIO pin. Returns `NeotrellisError::InvalidPin` if
+    /// `pin` is not in 0-31.
+    pub fn digital_write(&mut self, pin: u8, high: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.digital_write_bulk(pin_mask(pin)?, high)
+    }
+
+    /// Sets the output level of every Seesaw GPIO pin in `mask`. Other pins are unchanged.
+    pub fn digital_write_bulk(&mut self, mask: u32, high: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        let register = if high { GpioRegister::BulkSet } else { GpioRegister::BulkClear };
+        write(&mut self.i2c, self.address, &bulk_command(register, mask))
+    }
+
+    /// Inverts the output level of every Seesaw GPIO pin in `mask`.
+    pub fn toggle_bulk(&mut self, mask: u32) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &bulk_command(GpioRegister::BulkToggle, mask))
+    }
+
+    /// Delay-based function that reads the level of a Seesaw GPIO pin. Returns
+    /// `NeotrellisError::InvalidPin` if `pin` is not in 0-31.
+    pub fn digital_read(&mut self, pin: u8) -> Result<bool, NeotrellisError<I2C::Error>> {
+        let mask = pin_mask(pin)?;
+        Ok(self.digital_read_bulk(mask)? != 0)
+    }
+
+    /// Delay-based function that reads the level of every Seesaw GPIO pin in `mask`, with
+    /// bit n of the result set if pin n is high.
+    pub fn digital_read_bulk(&mut self, mask: u32) -> Result<u32, NeotrellisError<I2C::Error>> {
+        self.digital_read_bulk_write()?;
+        self.delay.delay_us(READ_DELAY_US);
+        Ok(self.digital_read_bulk_read()? & mask)
+    }
+
+    /// The write part of the `digital_read_bulk` function.
+    pub fn digital_read_bulk_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &[GPIO_REGISTER_BASE, GpioRegister::Bulk as u8])
+    }
+
+    /// The read operation corresponding to `digital_read_bulk_write`, returning the level of
+    /// every pin.
+    pub fn digital_read_bulk_read(&mut self) -> Result<u32, NeotrellisError<I2C::Error>> {
+        read_u32(&mut self.i2c, self.address)
+    }
+
+    /// Enables or disables the interrupt of every Seesaw GPIO pin in `mask`. While a pin's
+    /// interrupt is enabled, a change of its level pulls the board's INT line low until
+    /// the flags are read with `gpio_interrupt_flags`.
+    pub fn set_gpio_interrupts(&mut self, mask: u32, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        let register = if enabled { GpioRegister::InterruptEnableSet } else { GpioRegister::InterruptEnableClear };
+        write(&mut self.i2c, self.address, &bulk_command(register, mask))
+    }
+
+    /// Delay-based function that returns the mask of the Seesaw GPIO pins that have changed
+    /// since the last call, and clears them.
+    pub fn gpio_interrupt_flags(&mut self) -> Result<u32, NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &[GPIO_REGISTER_BASE, GpioRegister::InterruptFlag as u8])?;
+        self.delay.delay_us(READ_DELAY_US);
+        read_u32(&mut self.i2c, self.address)
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> crate::asynch::Seesaw<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs
+{
+    /// Configures a Seesaw GPIO pin. Returns `NeotrellisError::InvalidPin` if `pin` is not in 0-31.
+    pub async fn pin_mode(&mut self, pin: u8, mode: PinMode) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.pin_mode_bulk(pin_mask(pin)?, mode).await
+    }
+
+    /// Configures every Seesaw GPIO pin in `mask`, where bit n is pin n.
+    pub async fn pin_mode_bulk(&mut self, mask: u32, mode: PinMode) -> Result<(), NeotrellisError<I2C::Error>> {
+        for command in pin_mode_commands(mask, mode).iter().flatten() {
+            asynch::write(&mut self.i2c, self.address, command).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the output level of a Seesaw GPIO pin. Returns `NeotrellisError::InvalidPin` if
+    /// `pin` is not in 0-31.
+    pub async fn digital_write(&mut self, pin: u8, high: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.digital_write_bulk(pin_mask(pin)?, high).await
+    }
+
+    /// Sets the output level of every Seesaw GPIO pin in `mask`. Other pins are unchanged.
+    pub async fn digital_write_bulk(&mut self, mask: u32, high: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        let register = if high { GpioRegister::BulkSet } else { GpioRegister::BulkClear };
+        asynch::write(&mut self.i2c, self.address, &bulk_command(register, mask)).await
+    }
+
+    /// Inverts the output level of every Seesaw GPIO pin in `mask`.
+    pub async fn toggle_bulk(&mut self, mask: u32) -> Result<(), NeotrellisError<I2C::Error>> {
+        asynch::write(&mut self.i2c, self.address, &bulk_command(GpioRegister::BulkToggle, mask)).await
+    }
+
+    /// Reads the level of a Seesaw GPIO pin. Returns `NeotrellisError::InvalidPin` if `pin`
+    /// is not in 0-31.
+    pub async fn digital_read(&mut self, pin: u8) -> Result<bool, NeotrellisError<I2C::Error>> {
+        let mask = pin_mask(pin)?;
+        Ok(self.digital_read_bulk(mask).await? != 0)
+    }
+
+    /// Reads the level of every Seesaw GPIO pin in `mask`, with bit n of the result set if
+    /// pin n is high.
+    pub async fn digital_read_bulk(&mut self, mask: u32) -> Result<u32, NeotrellisError<I2C::Error>> {
+        self.digital_read_bulk_write().await?;
+        self.delay.delay_us(READ_DELAY_US).await;
+        Ok(self.digital_read_bulk_read().await? & mask)
+    }
+
+    /// The write part of the `digital_read_bulk` function.
+    pub async fn digital_read_bulk_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        asynch::write(&mut self.i2c, self.address, &[GPIO_REGISTER_BASE, GpioRegister::Bulk as u8]).await
+    }
+
+    /// The read operation corresponding to `digital_read_bulk_write`, returning the level of
+    /// every pin.
+    pub async fn digital_read_bulk_read(&mut self) -> Result<u32, NeotrellisError<I2C::Error>> {
+        asynch::read_u32(&mut self.i2c, self.address).await
+    }
+
+    /// Enables or disables the interrupt of every Seesaw GPIO pin in `mask`.
+    pub async fn set_gpio_interrupts(&mut self, mask: u32, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        let register = if enabled { GpioRegister::InterruptEnableSet } else { GpioRegister::InterruptEnableClear };
+        asynch::write(&mut self.i2c, self.address, &bulk_command(register, mask)).await
+    }
+
+    /// Returns the mask of the Seesaw GPIO pins that have changed since the last call, and
+    /// clears them.
+    pub async fn gpio_interrupt_flags(&mut self) -> Result<u32, NeotrellisError<I2C::Error>> {
+        asynch::write(&mut self.i2c, self.address, &[GPIO_REGISTER_BASE, GpioRegister::InterruptFlag as u8]).await?;
+        self.delay.delay_us(READ_DELAY_US).await;
+        asynch::read_u32(&mut self.i2c, self.address).await
+    }
+}
+
+/// A single Seesaw GPIO pin that implements the embedded-hal `InputPin` and `OutputPin`
+/// traits, so that buttons and LEDs wired to the board's pads can be used by other drivers.
+///
+/// Pins share the `Neotrellis`, or other `SeesawDevice`, through a `RefCell`; every read or
+/// write borrows it for the duration of the transaction.
+///
+/// Example usage: a button on pin 2 turning on an LED on pin 3:
+/// ```ignore
+/// let nt = RefCell::new(Neotrellis::new(i2c, delay, None));
+/// let mut button = GpioPin::new(&nt, 2, PinMode::InputPullUp).unwrap();
+/// let mut led = GpioPin::new(&nt, 3, PinMode::Output).unwrap();
+///
+/// if button.is_low().unwrap() { led.set_high().unwrap(); }
+/// ```
+pub struct GpioPin<'a, S> {
+    device: &'a RefCell<S>,
+    pin: u8
+}
+
+/// The error type of a `GpioPin` of the device `S`.
+type PinError<S> = NeotrellisError<<<S as SeesawDevice>::I2c as embedded_hal::i2c::ErrorType>::Error>;
+
+impl <'a, S: SeesawDevice> GpioPin<'a, S> {
+    /// Configures `pin` with `mode` and returns a proxy for it. Returns
+    /// `NeotrellisError::InvalidPin` if `pin` is not in 0-31.
+    pub fn new(device: &'a RefCell<S>, pin: u8, mode: PinMode) -> Result<Self, PinError<S>> {
+        device.borrow_mut().seesaw().pin_mode(pin, mode)?;
+        Ok(GpioPin { device, pin })
+    }
+
+    /// The Seesaw GPIO number of the pin.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Changes the configuration of the pin.
+    pub fn set_mode(&mut self, mode: PinMode) -> Result<(), PinError<S>> {
+        self.device.borrow_mut().seesaw().pin_mode(self.pin, mode)
+    }
+}
+
+impl <E: core::fmt::Debug> digital::Error for NeotrellisError<E> {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl <S: SeesawDevice> ErrorType for GpioPin<'_, S> {
+    type Error = PinError<S>;
+}
+
+impl <S: SeesawDevice> InputPin for GpioPin<'_, S> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.device.borrow_mut().seesaw().digital_read(self.pin)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl <S: SeesawDevice> OutputPin for GpioPin<'_, S> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.device.borrow_mut().seesaw().digital_write(self.pin, false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.device.borrow_mut().seesaw().digital_write(self.pin, true)
+    }
+}
+
+fn pin_mask<E>(pin: u8) -> Result<u32, NeotrellisError<E>> {
+    if pin >= 32 { return Err(NeotrellisError::InvalidPin); }
+    Ok(1 << pin)
+}
+
+fn bulk_command(register: GpioRegister, mask: u32) -> [u8; 6] {
+    let mask = mask.to_be_bytes();
+    [GPIO_REGISTER_BASE, register as u8, mask[0], mask[1], mask[2], mask[3]]
+}
+
+/// The writes that configure the pins in `mask`; the Seesaw sets the pull direction of an
+/// input with its output level.
+fn pin_mode_commands(mask: u32, mode: PinMode) -> [Option<[u8; 6]>; 3] {
+    match mode {
+        PinMode::Output => [Some(bulk_command(GpioRegister::DirectionSet, mask)), None, None],
+        PinMode::Input => [
+            Some(bulk_command(GpioRegister::DirectionClear, mask)),
+            Some(bulk_command(GpioRegister::PullEnableClear, mask)),
+            None
+        ],
+        PinMode::InputPullUp => [
+            Some(bulk_command(GpioRegister::DirectionClear, mask)),
+            Some(bulk_command(GpioRegister::PullEnableSet, mask)),
+            Some(bulk_command(GpioRegister::BulkSet, mask))
+        ],
+        PinMode::InputPullDown => [
+            Some(bulk_command(GpioRegister::DirectionClear, mask)),
+            Some(bulk_command(GpioRegister::PullEnableSet, mask)),
+            Some(bulk_command(GpioRegister::BulkClear, mask))
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+
+    #[test]
+    fn bulk_pin_modes_reads_and_writes() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.pin_mode_bulk(0b0011, PinMode::Output).unwrap();
+        nt.pin_mode_bulk(0b1100, PinMode::InputPullUp).unwrap();
+        nt.digital_write_bulk(0b0011, true).unwrap();
+        nt.toggle_bulk(0b0001).unwrap();
+        bus.borrow_mut().board_mut(0x2E).unwrap().set_gpio_input(3, false);
+
+        assert_eq!(nt.digital_read_bulk(0b1111).unwrap(), 0b0110);
+        assert!(!nt.digital_read(3).unwrap());
+        assert_eq!(nt.digital_read(32), Err(NeotrellisError::InvalidPin));
+    }
+
+    #[test]
+    fn pins_work_through_the_embedded_hal_traits() {
+        let bus = single_board_bus();
+        let nt = RefCell::new(neotrellis(&bus));
+
+        let mut button = GpioPin::new(&nt, 2, PinMode::InputPullUp).unwrap();
+        let mut led = GpioPin::new(&nt, 5, PinMode::Output).unwrap();
+        nt.borrow_mut().set_gpio_interrupts(1 << 2, true).unwrap();
+
+        assert!(button.is_high().unwrap());
+        bus.borrow_mut().board_mut(0x2E).unwrap().set_gpio_input(2, false);
+        assert!(bus.borrow().interrupt_asserted());
+        assert!(button.is_low().unwrap());
+
+        led.set_high().unwrap();
+        assert!(bus.borrow().board(0x2E).unwrap().gpio_output(5));
+
+        assert_eq!(nt.borrow_mut().gpio_interrupt_flags().unwrap(), 1 << 2);
+        assert!(!bus.borrow().interrupt_asserted());
+    }
+}
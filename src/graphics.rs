@@ -0,0 +1,172 @@
+//! `embedded-graphics` support, which requires the `embedded-graphics-core` feature.
+//!
+//! `Neotrellis` is a 4x4 `DrawTarget` and `MultiTrellis` one of its whole grid, with
+//! `Rgb888` colors, so primitives, images and fonts can be drawn on the keys. Point (0, 0)
+//! is the top left key and x grows to the right. Pixels outside of the grid are ignored.
+//!
+//! A `Neotrellis` draws into its framebuffer, so nothing is sent until `flush` is called.
+//! A `MultiTrellis` writes each pixel to its board with `set_led`, so `refresh_leds` must
+//! be called to show them.
+//!
+//! Example usage: drawing an arrow:
+//! ```ignore
+//! Triangle::new(Point::new(0, 0), Point::new(3, 0), Point::new(1, 3))
+//!     .into_styled(PrimitiveStyle::with_fill(Rgb888::CSS_ORANGE))
+//!     .draw(&mut nt)
+//!     .unwrap();
+//! nt.flush().unwrap();
+//! ```
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::Pixel;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::{Color, MultiTrellis, Neotrellis, NeotrellisError};
+
+impl From<Rgb888> for Color {
+    fn from(color: Rgb888) -> Self {
+        Color::rgb(color.r(), color.g(), color.b())
+    }
+}
+
+impl From<Color> for Rgb888 {
+    fn from(color: Color) -> Self {
+        Rgb888::new(color.r, color.g, color.b)
+    }
+}
+
+/// The LED index of a point on a 4x4 board, if it is on the board.
+fn led_index(x: i32, y: i32) -> Option<u8> {
+    if !(0..4).contains(&x) || !(0..4).contains(&y) { return None; }
+    Some((y * 4 + x) as u8)
+}
+
+impl <I2C, D> OriginDimensions for Neotrellis<I2C, D> {
+    fn size(&self) -> Size {
+        Size::new(4, 4)
+    }
+}
+
+impl <I2C: I2c, D: DelayNs> DrawTarget for Neotrellis<I2C, D> {
+    type Color = Rgb888;
+    type Error = NeotrellisError<I2C::Error>;
+
+    fn draw_iter<P>(&mut self, pixels: P) -> Result<(), Self::Error>
+    where
+        P: IntoIterator<Item = Pixel<Rgb888>>
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(led_index) = led_index(point.x, point.y) { self.set_pixel(led_index, color)?; }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Self::Error> {
+        self.fill(color);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> OriginDimensions for crate::asynch::Neotrellis<I2C, D> {
+    fn size(&self) -> Size {
+        Size::new(4, 4)
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> DrawTarget for crate::asynch::Neotrellis<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs
+{
+    type Color = Rgb888;
+    type Error = NeotrellisError<I2C::Error>;
+
+    fn draw_iter<P>(&mut self, pixels: P) -> Result<(), Self::Error>
+    where
+        P: IntoIterator<Item = Pixel<Rgb888>>
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(led_index) = led_index(point.x, point.y) { self.set_pixel(led_index, color)?; }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb888) -> Result<(), Self::Error> {
+        self.fill(color);
+        Ok(())
+    }
+}
+
+impl <I2C, D, const ROWS: usize, const COLS: usize> OriginDimensions for MultiTrellis<I2C, D, ROWS, COLS> {
+    fn size(&self) -> Size {
+        Size::new((COLS * 4) as u32, (ROWS * 4) as u32)
+    }
+}
+
+impl <I2C: I2c, D: DelayNs, const ROWS: usize, const COLS: usize> DrawTarget for MultiTrellis<I2C, D, ROWS, COLS> {
+    type Color = Rgb888;
+    type Error = NeotrellisError<I2C::Error>;
+
+    fn draw_iter<P>(&mut self, pixels: P) -> Result<(), Self::Error>
+    where
+        P: IntoIterator<Item = Pixel<Rgb888>>
+    {
+        for Pixel(point, color) in pixels {
+            let on_grid = (0..(COLS * 4) as i32).contains(&point.x) && (0..(ROWS * 4) as i32).contains(&point.y);
+            if on_grid { self.set_led(point.x as usize, point.y as usize, color)?; }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle};
+
+    use super::*;
+    use crate::sim::fixtures::{multi_board_bus, multitrellis, neotrellis, single_board_bus};
+
+    #[test]
+    fn primitives_are_drawn_into_the_framebuffer() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        assert_eq!(nt.size(), Size::new(4, 4));
+
+        // The line runs off the grid, which is ignored
+        Line::new(Point::new(0, 1), Point::new(6, 1))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::RED, 1))
+            .draw(&mut nt)
+            .unwrap();
+
+        assert_eq!(nt.pixel(4), Some(Color::RED));
+        assert_eq!(nt.pixel(7), Some(Color::RED));
+        assert_eq!(nt.pixel(8), Some(Color::BLACK));
+        assert!(bus.borrow().transactions().is_empty());
+    }
+
+    #[test]
+    fn multitrellis_draws_across_boards() {
+        let bus = multi_board_bus([[0x2E, 0x2F]]);
+        let mut mt = multitrellis(&bus, [[0x2E, 0x2F]]);
+        assert_eq!(mt.size(), Size::new(8, 4));
+
+        Rectangle::new(Point::new(3, 2), Size::new(2, 1))
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::BLUE))
+            .draw(&mut mt)
+            .unwrap();
+        mt.refresh_leds().unwrap();
+
+        let bus = bus.borrow();
+        assert_eq!(bus.board(0x2E).unwrap().pixel(11), (0, 0, 255));
+        assert_eq!(bus.board(0x2F).unwrap().pixel(8), (0, 0, 255));
+    }
+}
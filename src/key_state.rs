@@ -0,0 +1,189 @@
+use crate::{NeotrellisEvent, NeotrellisEventType};
+
+/// Tracks which keys are held down from the stream of `NeotrellisEvent`s.
+///
+/// Each event updates the raw state of its key. The held state follows the raw state,
+/// except that when a debounce window is set, a key that changed less than the window
+/// ago keeps its held state; the raw state is then taken on by `tick` once the window has
+/// passed. The first edge of a press or release is therefore reported straight away and
+/// the bounces after it are ignored.
+///
+/// If the keypad FIFO overflows, edge events are lost and keys can appear stuck. An edge
+/// that does not change the raw state of its key (e.g. a press of a key that is already
+/// held) means an event was missed and is counted by `missed_edges`. The state can be
+/// corrected with `resync`, `release_all`, or by enabling `KeyEdges::HIGH`/`KeyEdges::LOW`
+/// level events, which always set the state of their key.
+///
+/// Example usage:
+/// ```ignore
+/// let mut keys = KeyState::with_debounce(5);
+///
+/// loop {
+///     let now = timer.now_ms();
+///     for event in nt.key_event_iterate(&mut raw_events).unwrap() {
+///         keys.update(&event, now);
+///     }
+///     keys.tick(now);
+///
+///     if keys.is_pressed(0) && keys.is_pressed(3) { copy(); }
+/// }
+/// ```
+pub struct KeyState {
+    held: u16,
+    raw: u16,
+    changed_at: [Option<u32>; 16],
+    debounce_ms: u32,
+    missed_edges: u32
+}
+
+impl KeyState {
+    /// Creates a tracker with every key released and no debouncing.
+    pub fn new() -> Self {
+        Self::with_debounce(0)
+    }
+
+    /// Creates a tracker with every key released that ignores changes to a key within
+    /// `debounce_ms` milliseconds of its last change.
+    pub fn with_debounce(debounce_ms: u32) -> Self {
+        KeyState { held: 0, raw: 0, changed_at: [None; 16], debounce_ms, missed_edges: 0 }
+    }
+
+    /// Updates the state of the key that generated `event`, which was read at `now_ms`, a
+    /// free-running millisecond timestamp like the one `GestureDetector` and the `animation`
+    /// effects take. Returns true if the held state of the key changed.
+    pub fn update(&mut self, event: &NeotrellisEvent, now_ms: u32) -> bool {
+        if event.key_index >= 16 { return false; }
+
+        let bit = 1 << event.key_index;
+        let pressed = match event.event_type {
+            NeotrellisEventType::KeyPress | NeotrellisEventType::KeyHigh => true,
+            NeotrellisEventType::KeyRelease | NeotrellisEventType::KeyLow => false
+        };
+
+        let is_edge = matches!(event.event_type, NeotrellisEventType::KeyPress | NeotrellisEventType::KeyRelease);
+        if is_edge && (self.raw & bit != 0) == pressed {
+            self.missed_edges = self.missed_edges.wrapping_add(1);
+        }
+
+        if pressed { self.raw |= bit; } else { self.raw &= !bit; }
+        self.settle(event.key_index, now_ms)
+    }
+
+    /// Lets keys whose debounce window has passed take on their raw state. Should be called
+    /// regularly when debouncing so that the final edge of a bounce is not missed. Returns a
+    /// mask of the keys whose held state changed.
+    pub fn tick(&mut self, now_ms: u32) -> u16 {
+        let mut changed = 0;
+        for key_index in 0..16 {
+            if self.settle(key_index, now_ms) { changed |= 1 << key_index; }
+        }
+
+        changed
+    }
+
+    /// A mask of the held keys, where bit n is set if key n is held.
+    pub fn held_mask(&self) -> u16 {
+        self.held
+    }
+
+    /// Returns true if the key is held. Keys outside of 0-15 are never held.
+    pub fn is_pressed(&self, key_index: usize) -> bool {
+        key_index < 16 && self.held & (1 << key_index) != 0
+    }
+
+    /// The number of press or release events that did not change the state of their key,
+    /// which means an event before them was lost.
+    pub fn missed_edges(&self) -> u32 {
+        self.missed_edges
+    }
+
+    /// Replaces the state of every key with `held_mask`, e.g. after events were lost.
+    pub fn resync(&mut self, held_mask: u16) {
+        self.held = held_mask;
+        self.raw = held_mask;
+        self.changed_at = [None; 16];
+    }
+
+    /// Marks every key as released.
+    pub fn release_all(&mut self) {
+        self.resync(0);
+    }
+
+    fn settle(&mut self, key_index: usize, now_ms: u32) -> bool {
+        let bit = 1 << key_index;
+        if (self.raw ^ self.held) & bit == 0 { return false; }
+
+        let settled = match self.changed_at[key_index] {
+            Some(changed_at) => now_ms.wrapping_sub(changed_at) >= self.debounce_ms,
+            None => true
+        };
+        if !settled { return false; }
+
+        self.held ^= bit;
+        self.changed_at[key_index] = Some(now_ms);
+        true
+    }
+}
+
+impl Default for KeyState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{press, release};
+
+    #[test]
+    fn tracks_held_keys() {
+        let mut keys = KeyState::new();
+
+        assert!(keys.update(&press(3), 0));
+        assert!(keys.update(&press(12), 0));
+        assert!(keys.update(&release(3), 0));
+
+        assert_eq!(keys.held_mask(), 1 << 12);
+        assert!(keys.is_pressed(12));
+        assert!(!keys.is_pressed(3));
+        assert!(!keys.is_pressed(16));
+        assert_eq!(keys.missed_edges(), 0);
+    }
+
+    #[test]
+    fn debounce_ignores_bounces_but_settles_on_the_final_state() {
+        let mut keys = KeyState::with_debounce(5);
+
+        assert!(keys.update(&press(0), 10));
+        assert!(!keys.update(&release(0), 11));
+        assert!(!keys.update(&press(0), 12));
+        assert!(!keys.update(&release(0), 13));
+        assert!(keys.is_pressed(0));
+
+        assert_eq!(keys.tick(14), 0);
+        assert_eq!(keys.tick(15), 1);
+        assert!(!keys.is_pressed(0));
+    }
+
+    #[test]
+    fn lost_events_are_counted_and_can_be_resynced() {
+        let mut keys = KeyState::new();
+
+        keys.update(&press(5), 0);
+        keys.update(&press(5), 0);
+        assert_eq!(keys.missed_edges(), 1);
+
+        keys.resync(0b11);
+        assert_eq!(keys.held_mask(), 0b11);
+
+        let low = NeotrellisEvent { key_index: 0, event_type: NeotrellisEventType::KeyLow };
+        keys.update(&low, 0);
+        keys.update(&low, 0);
+        assert_eq!(keys.held_mask(), 0b10);
+        assert_eq!(keys.missed_edges(), 1);
+
+        keys.release_all();
+        assert_eq!(keys.held_mask(), 0);
+    }
+}
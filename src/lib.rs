@@ -1,71 +1,146 @@
 //! Provides an embedded-hal I2C driver for the [Adafruit NeoTrellis RGB](https://www.adafruit.com/product/3954)
-//! key grid. This code is derived from the [Adafruit Seesaw](https://github.com/adafruit/Adafruit_Seesaw)
-//! repository but specialized for the use case of a
-//! [simple, singular NeoTrellis configuration](https://github.com/adafruit/Adafruit_Seesaw/blob/master/examples/NeoTrellis/basic/basic.ino).
+//! key grid, either a single board or several tiled into one larger grid. This code is
+//! derived from the [Adafruit Seesaw](https://github.com/adafruit/Adafruit_Seesaw) repository.
 //!
-//! Having multiple NeoTrellis boards linked is currently unsupported due to
-//! the single Neotrellis structure consuming the I2C structure. If you would
-//! like this feature, pull requests are encouraged.
+//! The Seesaw protocol itself, with the status and GPIO modules and public register maps
+//! for the other modules, lives in the `seesaw` module. Its `Seesaw` core can drive other
+//! Seesaw products, and `Neotrellis` is layered on top of it.
+//!
+//! Several NeoTrellis boards tiled on the same I2C bus can be driven as one large
+//! grid with the `MultiTrellis` structure, which addresses each board by its jumper
+//! address and works in global (x, y) coordinates.
+//!
+//! The drivers are built on the embedded-hal 1.0 `I2c` trait. HALs that only implement
+//! the embedded-hal 0.2 traits can be used through the `compat` module, which requires
+//! the `embedded-hal-02` feature.
+//!
+//! The drivers take ownership of the I2C bus they are given. To share the bus with
+//! other devices, give them a `&mut I2C`, or a `RefCellDevice` or `CriticalSectionDevice`
+//! from the `embedded-hal-bus` crate, or take the bus back with `release` when finished.
 //!
 //! The Adafruit Seesaw board requires delays between writes and reads. The
 //! application that this library was intended for is a synthesizer which
 //! cannot support such delays synchronously. For many of the Neotrellis functions,
 //! there is a more convenient write/read combo that guarantees the timing requirements
 //! as well as independent write and read functions that do not enforce a given delay.
+//! `KeyEventPoller` strings the keypad functions together into a non-blocking state
+//! machine that makes each step once its delay has elapsed.
+//! For the same reason, combined `write_read` transactions are not used: the Seesaw
+//! needs time to prepare its response between the register write and the read.
+//!
+//! The `animation` module provides time-driven effects, such as fades, breathing and
+//! rainbows, that render into the LED state of a driver and can be limited to some keys.
+//!
+//! A `Compositor` stacks layers of pixels with their own alpha, visibility and blend mode,
+//! and combines them into the LED colors, e.g. to show a cursor over a background pattern.
+//!
+//! The `text` module draws text in a tiny built-in font and scrolls it across the LEDs
+//! with a `Marquee`, e.g. to show a tempo across a row of tiled boards.
+//!
+//! With the `std` feature enabled, the `sim` module provides a simulated Seesaw I2C bus
+//! so that code using the drivers can be tested on a host machine.
+//!
+//! With the `async` feature enabled, the `asynch` module provides a Neotrellis driver
+//! built on the `embedded-hal-async` traits which awaits these delays instead.
+//!
+//! With the `smart-leds-trait` feature enabled, `Neotrellis` implements the `smart-leds`
+//! `SmartLedsWrite` trait (and `SmartLedsWriteAsync` for the async driver), so the grid can
+//! be driven by the animation crates of that ecosystem.
+//!
+//! With the `embedded-graphics-core` feature enabled, `Neotrellis` and `MultiTrellis` are
+//! `embedded-graphics` `DrawTarget`s of their key grids.
 
 #![no_std]
-use embedded_hal::blocking::i2c::{Write, Read};
-
-const STATUS_REGISTER_BASE: u8 = 0x00;
-const KEYPAD_REGISTER_BASE: u8 = 0x10;
-const NEOPIXEL_REGISTER_BASE: u8 = 0x0E;
-
-enum StatusRegister {
-    HardwareId = 0x01
-}
-
-enum KeypadRegister {
-    Event = 0x01,
-    Count = 0x04,
-    Fifo = 0x10
-}
-
-enum NeopixelRegister {
-    Pin = 0x01,
-    Speed = 0x02,
-    BufferLength = 0x03,
-    Buffer = 0x04,
-    Show = 0x05
-}
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::InputPin;
+use embedded_hal::i2c::I2c;
+
+pub mod animation;
+pub mod delay;
+mod color;
+use color::ColorCorrection;
+pub use color::{Color, GAMMA_2_8};
+mod compositor;
+pub use compositor::{BlendMode, Compositor, Layer};
+mod error;
+pub use error::NeotrellisError;
+mod framebuffer;
+use framebuffer::Framebuffer;
+#[cfg(feature = "embedded-graphics-core")]
+mod graphics;
+mod gpio;
+pub use gpio::{GpioPin, PinMode};
+mod gesture;
+pub use gesture::{Gesture, GestureConfig, GestureDetector};
+mod key_state;
+pub use key_state::KeyState;
+#[cfg(feature = "embedded-hal-02")]
+pub mod compat;
+#[cfg(feature = "async")]
+pub mod asynch;
+mod multitrellis;
+pub use multitrellis::{MultiTrellis, MultiTrellisEvent, MultiTrellisEventIterator};
+mod poll;
+pub use poll::KeyEventPoller;
+pub mod seesaw;
+#[cfg(feature = "smart-leds-trait")]
+mod smart_leds;
+pub use seesaw::{Seesaw, SeesawDevice};
+use seesaw::{
+    hardware_id,
+    read,
+    read_byte,
+    write,
+    KeypadRegister,
+    NeopixelRegister,
+    KEYPAD_REGISTER_BASE,
+    NEOPIXEL_REGISTER_BASE,
+    READ_DELAY_US
+};
+mod status;
+pub use status::{FirmwareVersion, Options};
+pub mod text;
+#[cfg(any(test, feature = "std"))]
+pub mod sim;
+
+const DEFAULT_ADDRESS: u8 = 0x2E;
+const HARDWARE_ID: u8 = 0x55;
 
 /// Main structure used for interfacing with the Neotrellis RGB board.
 ///
+/// A Neotrellis dereferences to its `Seesaw` core, so the status and GPIO functions and
+/// the public `delay` are available on it directly.
+///
 /// Example usage: turning an LED white while it is pressed:
-/// ```
-/// let mut nt = Neotrellis::new(i2c, delay_us, None);
+/// ```ignore
+/// let mut nt = Neotrellis::new(i2c, delay, None);
 /// nt.initialize().unwrap();
 ///
 /// loop {
-///     delay(20_000);
+///     nt.delay.delay_us(20_000);
 ///
 ///     let mut raw_events: [u8; 16] = [0; 16];
 ///     for event in nt.key_event_iterate(&mut raw_events).unwrap() {
 ///         match event.event_type {
 ///             NeotrellisEventType::KeyPress =>
-///                 nt.set_led(event.key_index as u8, 106, 13, 173).unwrap(),
+///                 nt.set_led(event.key_index as u8, Color::PURPLE).unwrap(),
 ///             NeotrellisEventType::KeyRelease =>
-///                 nt.set_led(event.key_index as u8, 0, 0, 0).unwrap()
+///                 nt.set_led(event.key_index as u8, Color::BLACK).unwrap(),
+///             _ => {}
 ///         };
 ///     }
 ///
-///     delay(500);
+///     nt.delay.delay_us(300);
 ///     nt.refresh_leds().unwrap();
 /// }
 /// ```
-pub struct Neotrellis<I2C> {
-    i2c: I2C,
-    address: u8,
-    delay_function: fn(u32)
+pub struct Neotrellis<I2C, D> {
+    seesaw: Seesaw<I2C, D>,
+    framebuffer: Framebuffer,
+    correction: ColorCorrection
 }
 
 fn neo_trellis_index(index: usize) -> u8 {
@@ -79,35 +154,137 @@ fn see_saw_index(index: u8) -> u8 {
 /// Iterator returned by the `key_event_iterate` and `key_event_iterate_read` functions
 /// of the Neotrellis struct. Iterates through the raw bytes returned by the Neotrellis
 /// event FIFO and turns them into NeotrellisEvents.
+///
+/// Raw bytes that do not name one of the 16 keys, such as the 0xFF the Seesaw sends once
+/// its FIFO is empty, are skipped and counted by `dropped`.
 pub struct NeotrellisEventIterator<'a> {
     index: usize,
-    raw_events: &'a [u8]
+    raw_events: &'a [u8],
+    deferred: usize
+}
+
+impl <'a> NeotrellisEventIterator<'a> {
+    pub(crate) fn new(raw_events: &'a [u8], deferred: usize) -> Self {
+        NeotrellisEventIterator { index: 0, raw_events, deferred }
+    }
+
+    pub(crate) fn empty() -> Self {
+        NeotrellisEventIterator::new(&[], 0)
+    }
+
+    /// The number of raw bytes read from the FIFO that were not key events and are skipped.
+    pub fn dropped(&self) -> usize {
+        self.raw_events.iter().filter(|&&raw| !is_key_event(raw)).count()
+    }
+
+    /// The number of events that did not fit in the event buffer. They are left in the
+    /// keypad FIFO and returned by the next read.
+    pub fn deferred(&self) -> usize {
+        self.deferred
+    }
+}
+
+/// The outcome of `Neotrellis::key_event_drain` or `MultiTrellis::key_event_drain`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrainReport {
+    /// The number of events handed to the callback.
+    pub events: usize,
+
+    /// The number of raw bytes read from the FIFO that were not key events.
+    pub dropped: usize,
+
+    /// The number of FIFO reads made.
+    pub reads: usize
+}
+
+impl DrainReport {
+    /// Hands the events of one FIFO read to `on_event` and counts them.
+    pub(crate) fn record<E>(&mut self, dropped: usize, events: impl Iterator<Item = E>, on_event: &mut impl FnMut(E)) {
+        self.dropped += dropped;
+        self.reads += 1;
+        for event in events {
+            on_event(event);
+            self.events += 1;
+        }
+    }
 }
 
 /// Event type of a `NeotrellisEvent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NeotrellisEventType {
     /// The event occurred because of a keypad being pressed.
     KeyPress,
 
     /// The event occurred because of a keypad being released.
-    KeyRelease
+    KeyRelease,
+
+    /// The event occurred because a keypad was held down while the `KeyEdges::HIGH`
+    /// event was enabled for it.
+    KeyHigh,
+
+    /// The event occurred because a keypad was up while the `KeyEdges::LOW` event was
+    /// enabled for it.
+    KeyLow
+}
+
+/// A set of keypad edges that generate events, used with `Neotrellis::configure_key`.
+///
+/// Sets can be combined with `|`, e.g. `KeyEdges::RISING | KeyEdges::FALLING`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEdges(u8);
+
+impl KeyEdges {
+    /// No edges.
+    pub const NONE: KeyEdges = KeyEdges(0);
+
+    /// Level event generated while the key is held down; `NeotrellisEventType::KeyHigh`.
+    pub const HIGH: KeyEdges = KeyEdges(1 << 0);
+
+    /// Level event generated while the key is up; `NeotrellisEventType::KeyLow`.
+    pub const LOW: KeyEdges = KeyEdges(1 << 1);
+
+    /// Edge event generated when the key is released; `NeotrellisEventType::KeyRelease`.
+    pub const FALLING: KeyEdges = KeyEdges(1 << 2);
+
+    /// Edge event generated when the key is pressed; `NeotrellisEventType::KeyPress`.
+    pub const RISING: KeyEdges = KeyEdges(1 << 3);
+
+    /// All four edges.
+    pub const ALL: KeyEdges = KeyEdges(0b1111);
+
+    /// Returns true if every edge in `other` is also in this set.
+    pub fn contains(self, other: KeyEdges) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for KeyEdges {
+    type Output = KeyEdges;
+
+    fn bitor(self, other: KeyEdges) -> KeyEdges {
+        KeyEdges(self.0 | other.0)
+    }
 }
 
 /// A structure that represents a Neotrellis keypad event.
 ///
 /// Events are generated whenever a keypad key is pressed or released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct NeotrellisEvent {
     /// The index of the keypad key that generated the event, 0-15.
     pub key_index: usize,
 
-    /// The type of the event; KeyPress or KeyRelease.
+    /// The type of the event; KeyPress or KeyRelease unless other edges have been
+    /// enabled with `configure_key`.
     pub event_type: NeotrellisEventType
 }
 
 impl From<u8> for NeotrellisEvent {
     fn from(input: u8) -> Self {
-        let event_type = match input & 0x01 {
-            0 => NeotrellisEventType::KeyRelease,
+        let event_type = match input & 0x03 {
+            0 => NeotrellisEventType::KeyHigh,
+            1 => NeotrellisEventType::KeyLow,
+            2 => NeotrellisEventType::KeyRelease,
             _ => NeotrellisEventType::KeyPress
         };
 
@@ -121,52 +298,53 @@ impl <'a> Iterator for NeotrellisEventIterator<'a> {
     type Item = NeotrellisEvent;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.raw_events.len() { return None; }
-
-        let event_parsed = NeotrellisEvent::from(self.raw_events[self.index]);
-        self.index += 1;
+        while self.index < self.raw_events.len() {
+            let raw = self.raw_events[self.index];
+            self.index += 1;
+            if is_key_event(raw) { return Some(NeotrellisEvent::from(raw)); }
+        }
 
-        Some(event_parsed)
+        None
     }
 }
 
-impl <I2C, E> Neotrellis <I2C> where I2C: Write<Error = E> + Read<Error = E> {
+/// Returns true if a raw FIFO byte is an event of one of the 16 keys. The Seesaw numbers
+/// the keys in rows of 8, of which the Neotrellis uses the first 4 columns and rows.
+pub(crate) fn is_key_event(raw: u8) -> bool {
+    let index = raw >> 2;
+    index / 8 < 4 && index % 8 < 4
+}
+
+impl <I2C: I2c, D: DelayNs> Neotrellis<I2C, D> {
     /// Creates a new Neotrellis struct which can be used communicate with the neotrellis board.
     ///
     /// The Neotrellis specifies that it requires some delays in between writes and reads. The
-    /// `delay_function` parameter should be a function that can be called to delay for some
-    /// number of microseconds.
+    /// `delay` parameter can be any `DelayNs` implementation, such as a HAL timer. A plain
+    /// function or closure taking a number of microseconds can be used by wrapping it in
+    /// `delay::FnDelay`.
     ///
     /// The address will default to 0x2E (the default address with no jumpers). If the Neotrellis
     /// has another address due to jumpers, it can be given in the `custom_address` parameter.
-    pub fn new(i2c: I2C, delay_function: fn(u32), custom_address: Option<u8>) -> Neotrellis<I2C> {
-        Neotrellis { i2c, delay_function, address: custom_address.unwrap_or(0x2E) }
+    pub fn new(i2c: I2C, delay: D, custom_address: Option<u8>) -> Neotrellis<I2C, D> {
+        Neotrellis {
+            seesaw: Seesaw::new(i2c, delay, custom_address.unwrap_or(DEFAULT_ADDRESS)),
+            framebuffer: Framebuffer::new(),
+            correction: ColorCorrection::NONE
+        }
     }
 
-    /// Checks if the Neotrellis board is communicable and sends several commands to initialize
-    /// it to use its keypad and LED array. This function uses delays and has an assertion that
-    /// the board is communicating correctly.
-    pub fn initialize(&mut self) -> Result<(), E> {
-        // Make sure it is working
-        assert_eq!(self.hardware_id()?, 0x55);
-
-        // Activate all keys on the trellis
-        for key in 0..16 { self.key_event_enable(key)? }
-
-        // Start up and clear the leds
-        self.enable_leds()?;
-        self.clear_leds()?;
-        (self.delay_function)(300);
-        self.refresh_leds()?;
-
-        Ok(())
+    /// Consumes the Neotrellis and hands back the I2C bus and delay it was created with.
+    pub fn release(self) -> (I2C, D) {
+        self.seesaw.release()
     }
 
-    /// Delay-based function that checks the hardware ID of the Neotrellis. A value of 0x55 is expected.
-    pub fn hardware_id(&mut self) -> Result<u8, E> {
-        self.hardware_id_write()?;
-        (self.delay_function)(500);
-        self.hardware_id_read()
+    /// Checks if the Neotrellis board is communicable and sends several commands to initialize
+    /// it to use its keypad and LED array. This function uses delays and returns
+    /// `NeotrellisError::WrongHardwareId` if another device answers at the address.
+    pub fn initialize(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        initialize(&mut self.seesaw.i2c, self.seesaw.address, &mut self.seesaw.delay)?;
+        self.framebuffer.clear();
+        Ok(())
     }
 
     /// Delay-based write/read operation that checks how many events have been generated
@@ -176,136 +354,581 @@ impl <I2C, E> Neotrellis <I2C> where I2C: Write<Error = E> + Read<Error = E> {
     ///
     /// The `event_buffer` passed in will be filled with the raw events and the event
     /// iterator will use it to read out the parsed events.
-    pub fn key_event_iterate<'a>(&mut self, event_buffer: &'a mut [u8]) -> Result<NeotrellisEventIterator<'a>, E> {
+    pub fn key_event_iterate<'a>(&mut self, event_buffer: &'a mut [u8]) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
         let count = self.key_event_count()?;
-        if count == 0 {
-            return Ok(NeotrellisEventIterator { index: 0, raw_events: &event_buffer[..0] });
+        if count == 0 || event_buffer.is_empty() {
+            return Ok(NeotrellisEventIterator::new(&[], count as usize));
         }
 
         self.key_event_iterate_write()?;
-        (self.delay_function)(500);
+        self.seesaw.delay.delay_us(READ_DELAY_US);
         self.key_event_iterate_read(event_buffer, count)
     }
 
-    /// Delay-based write/read operation that returns how many events have been
-    /// generated by the Neotrellis keypad.
-    pub fn key_event_count(&mut self) -> Result<u8, E> {
-        self.key_event_count_write()?;
-        (self.delay_function)(500);
-        self.key_event_count_read()
+    /// Delay-based write/read operation that empties the keypad FIFO, reading it in chunks
+    /// the size of `event_buffer` and handing each event to `on_event`. However small the
+    /// buffer, the events are read in order and none are lost.
+    ///
+    /// Only the events that were in the FIFO when it was called are read, so a key that
+    /// keeps generating events cannot keep it busy. Returns `NeotrellisError::BufferTooSmall`
+    /// if `event_buffer` is empty.
+    ///
+    /// Example usage: catching up after a long stall with a small buffer:
+    /// ```ignore
+    /// let mut raw_events: [u8; 4] = [0; 4];
+    /// let report = nt.key_event_drain(&mut raw_events, |event| key_state.update(&event)).unwrap();
+    /// ```
+    pub fn key_event_drain<F: FnMut(NeotrellisEvent)>(&mut self, event_buffer: &mut [u8], mut on_event: F) -> Result<DrainReport, NeotrellisError<I2C::Error>> {
+        if event_buffer.is_empty() { return Err(NeotrellisError::BufferTooSmall); }
+
+        let mut report = DrainReport::default();
+        for count in drain_chunks(self.key_event_count()?, event_buffer.len()) {
+            self.key_event_iterate_write()?;
+            self.seesaw.delay.delay_us(READ_DELAY_US);
+            let events = self.key_event_iterate_read(event_buffer, count)?;
+            report.record(events.dropped(), events, &mut on_event);
+        }
+
+        Ok(report)
     }
 
-    /// The `write` part of the `hardware_id` function.
-    pub fn hardware_id_write(&mut self) -> Result<(), E> {
-        let register_set = [STATUS_REGISTER_BASE, StatusRegister::HardwareId as u8];
-        self.i2c.write(self.address, &register_set)
+    /// Delay-based write/read operation like `key_event_iterate` that only talks to the
+    /// board if `interrupt_pin`, connected to the Neotrellis INT line, is low. Otherwise
+    /// an empty iterator is returned without using the bus.
+    ///
+    /// The keypad interrupt must have been enabled with `enable_key_interrupt` first.
+    pub fn key_event_iterate_on_interrupt<'a, P: InputPin>(&mut self, interrupt_pin: &mut P, event_buffer: &'a mut [u8]) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        if !interrupt_pin.is_low().map_err(|_| NeotrellisError::Pin)? {
+            return Ok(NeotrellisEventIterator::empty());
+        }
+
+        self.key_event_iterate(event_buffer)
+    }
+
+    /// Enables or disables events for the given `edges` of a key. Edges that are not in
+    /// `edges` keep their current setting. `initialize` enables the rising and falling edges
+    /// of every key. Returns `NeotrellisError::InvalidKey` if `key_index` is not in 0-15.
+    ///
+    /// Example usage: making key 0 a level-triggered button and silencing key 15:
+    /// ```ignore
+    /// nt.configure_key(0, KeyEdges::HIGH, true).unwrap();
+    /// nt.configure_key(15, KeyEdges::ALL, false).unwrap();
+    /// ```
+    pub fn configure_key(&mut self, key_index: usize, edges: KeyEdges, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        configure_key(&mut self.seesaw.i2c, self.seesaw.address, key_index, edges, enabled)
     }
 
-    /// The `read` operation corresponding to the `hardware_id_write` function.
-    pub fn hardware_id_read(&mut self) -> Result<u8, E> {
-        let mut read_buffer: [u8; 1] = [0; 1];
-        self.i2c
-            .read(self.address, &mut read_buffer)
-            .map(|_| read_buffer[0])
+    /// Makes the Neotrellis pull its INT line low while there are events in the keypad FIFO.
+    pub fn enable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.seesaw.i2c, self.seesaw.address, &key_interrupt_command(true))
     }
 
-    fn key_event_enable(&mut self, key_index: usize) -> Result<(), E> {
-        let command = [
-            KEYPAD_REGISTER_BASE,
-            KeypadRegister::Event as u8,
-            neo_trellis_index(key_index),
-            0b00011001 // Rising and Falling edge events enabled
-        ];
+    /// Stops the Neotrellis from pulling its INT line low for keypad events.
+    pub fn disable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.seesaw.i2c, self.seesaw.address, &key_interrupt_command(false))
+    }
 
-        self.i2c.write(self.address, &command)
+    /// Delay-based write/read operation that returns how many events have been
+    /// generated by the Neotrellis keypad.
+    pub fn key_event_count(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        key_event_count(&mut self.seesaw.i2c, self.seesaw.address, &mut self.seesaw.delay)
     }
 
     /// A write operation that sets the next read to see how many keypad events are in the FIFO.
-    pub fn key_event_count_write(&mut self) -> Result<(), E> {
-        let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Count as u8];
-        self.i2c.write(self.address, &command)
+    pub fn key_event_count_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        key_event_count_write(&mut self.seesaw.i2c, self.seesaw.address)
     }
 
     /// The read operation corresponding to `key_event_count_write`.
-    pub fn key_event_count_read(&mut self) -> Result<u8, E> {
-        let mut read_buffer: [u8; 1] = [0; 1];
-        self.i2c
-            .read(self.address, &mut read_buffer)
-            .map(|_| read_buffer[0])
+    pub fn key_event_count_read(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        read_byte(&mut self.seesaw.i2c, self.seesaw.address)
     }
 
     /// A write operation that sets the next read to the keypad event buffer.
-    pub fn key_event_iterate_write(&mut self) -> Result<(), E> {
-        let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Fifo as u8];
-        self.i2c.write(self.address, &command)
+    pub fn key_event_iterate_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        key_event_iterate_write(&mut self.seesaw.i2c, self.seesaw.address)
     }
 
     /// A read event that reads the events out to `event_buffer` and returns
     /// an iterator to these events.
-    pub fn key_event_iterate_read<'a>(&mut self, event_buffer: &'a mut [u8], count: u8) -> Result<NeotrellisEventIterator<'a>, E> {
-        self.i2c.read(self.address, &mut event_buffer[..count as usize])?;
-        Ok(NeotrellisEventIterator { index: 0, raw_events: &event_buffer[..count as usize] })
+    ///
+    /// At most `event_buffer.len()` of the `count` events are read. The rest are left in
+    /// the FIFO and reported by the iterator's `deferred`.
+    pub fn key_event_iterate_read<'a>(&mut self, event_buffer: &'a mut [u8], count: u8) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        let (raw_events, deferred) = event_chunk(event_buffer, count);
+        if !raw_events.is_empty() { read(&mut self.seesaw.i2c, self.seesaw.address, raw_events)?; }
+        Ok(NeotrellisEventIterator::new(raw_events, deferred))
     }
 
-    fn enable_leds(&mut self) -> Result<(), E> {
-        // Set the speed to 800khz
-        let command: [u8; 3] = [
-            NEOPIXEL_REGISTER_BASE,
-            NeopixelRegister::Speed as u8,
-            0x01
-        ];
-        self.i2c.write(self.address, &command)?;
+    /// Sets all LEDs on the Neotrellis to an RGB value of #000000
+    pub fn clear_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        clear_leds(&mut self.seesaw.i2c, self.seesaw.address)?;
+        self.framebuffer.clear();
+        Ok(())
+    }
 
-        // Update the length of the remote buffer
-        let command: [u8; 4] = [
-            NEOPIXEL_REGISTER_BASE,
-            NeopixelRegister::BufferLength as u8,
-            0,
-            16 * 3
-        ];
-        self.i2c.write(self.address, &command)?;
+    /// Sets an LED on the Neotrellis to a color. Returns `NeotrellisError::InvalidKey`
+    /// if `led_index` is not in 0-15.
+    pub fn set_led(&mut self, led_index: u8, color: impl Into<Color>) -> Result<(), NeotrellisError<I2C::Error>> {
+        let color = color.into();
+        set_led(&mut self.seesaw.i2c, self.seesaw.address, led_index, self.correction.apply(color))?;
+        self.framebuffer.store_pixel(led_index as usize, color);
+        Ok(())
+    }
+
+    /// Refreshes the LED display based on changes made in `clear_leds` and `set_led`.
+    /// Should be called 300us after these functions to allow time for the data to
+    /// latch.
+    pub fn refresh_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        refresh_leds(&mut self.seesaw.i2c, self.seesaw.address)
+    }
+
+    /// Sets a pixel of the local framebuffer to a color without writing it to the
+    /// board. The change is sent by the next `flush` or `flush_write`. Returns
+    /// `NeotrellisError::InvalidKey` if `led_index` is not in 0-15.
+    pub fn set_pixel(&mut self, led_index: u8, color: impl Into<Color>) -> Result<(), NeotrellisError<I2C::Error>> {
+        if led_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+
+        self.framebuffer.set_pixel(led_index as usize, color.into());
+        Ok(())
+    }
+
+    /// Returns the color of a pixel in the local framebuffer, or `None` if `led_index`
+    /// is not in 0-15.
+    pub fn pixel(&self, led_index: u8) -> Option<Color> {
+        if led_index >= 16 { return None; }
+
+        Some(self.framebuffer.pixel(led_index as usize))
+    }
+
+    /// Sets every pixel of the local framebuffer to a color without writing it to
+    /// the board.
+    pub fn fill(&mut self, color: impl Into<Color>) {
+        let color = color.into();
+        for index in 0..16 { self.framebuffer.set_pixel(index, color); }
+    }
+
+    /// Delay-based operation that writes the pixels changed since the last flush to the
+    /// board and refreshes the LED display. Nothing is sent if no pixels have changed.
+    pub fn flush(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        if !self.framebuffer.is_dirty() { return Ok(()); }
 
+        self.flush_write()?;
+        self.seesaw.delay.delay_us(300);
+        self.refresh_leds()
+    }
+
+    /// The write part of the `flush` function. Sends the smallest byte range covering the
+    /// changed pixels in as few writes as the Seesaw allows, without refreshing the display.
+    pub fn flush_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for (command, length) in self.framebuffer.dirty_chunks(self.correction) {
+            write(&mut self.seesaw.i2c, self.seesaw.address, &command[..length])?;
+        }
+
+        self.framebuffer.mark_clean();
+        Ok(())
+    }
+
+    /// Sets the brightness every color is scaled by before it is sent to the board, from 0
+    /// (off) to 255 (full, the default). Colors are kept as they were set, so the brightness
+    /// can be turned back up without losing them. The pixels already on the board are
+    /// resent with the new brightness by the next `flush`.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        if brightness == self.correction.brightness { return; }
+
+        self.correction.brightness = brightness;
+        self.framebuffer.mark_all_dirty();
+    }
+
+    /// The brightness set with `set_brightness`.
+    pub fn brightness(&self) -> u8 {
+        self.correction.brightness
+    }
+
+    /// Sets the gamma correction lookup table applied to each color component after the
+    /// brightness, such as `GAMMA_2_8`, or `None` to send the components as they are (the
+    /// default). As with `set_brightness`, the next `flush` resends every pixel.
+    pub fn set_gamma(&mut self, table: Option<&'static [u8; 256]>) {
+        if table == self.correction.gamma { return; }
+
+        self.correction.gamma = table;
+        self.framebuffer.mark_all_dirty();
+    }
+}
+
+impl <I2C, D> core::ops::Deref for Neotrellis<I2C, D> {
+    type Target = Seesaw<I2C, D>;
+
+    fn deref(&self) -> &Seesaw<I2C, D> {
+        &self.seesaw
+    }
+}
+
+impl <I2C, D> core::ops::DerefMut for Neotrellis<I2C, D> {
+    fn deref_mut(&mut self) -> &mut Seesaw<I2C, D> {
+        &mut self.seesaw
+    }
+}
+
+impl <I2C: I2c, D: DelayNs> SeesawDevice for Neotrellis<I2C, D> {
+    type I2c = I2C;
+    type Delay = D;
+
+    fn seesaw(&mut self) -> &mut Seesaw<I2C, D> {
+        &mut self.seesaw
+    }
+}
+
+// The register-level operations below take the bus and board address explicitly so
+// that they can be shared between `Neotrellis` and `MultiTrellis`.
+
+fn initialize<I2C: I2c>(i2c: &mut I2C, address: u8, delay: &mut impl DelayNs) -> Result<(), NeotrellisError<I2C::Error>> {
+    // Make sure it is working
+    let id = hardware_id(i2c, address, delay)?;
+    if id != HARDWARE_ID { return Err(NeotrellisError::WrongHardwareId(id)); }
+
+    // Activate all keys on the trellis
+    for key in 0..16 { configure_key(i2c, address, key, KeyEdges::RISING | KeyEdges::FALLING, true)? }
+
+    // Start up and clear the leds
+    enable_leds(i2c, address)?;
+    clear_leds(i2c, address)?;
+    delay.delay_us(300);
+    refresh_leds(i2c, address)?;
+
+    Ok(())
+}
+
+fn key_event_count<I2C: I2c>(i2c: &mut I2C, address: u8, delay: &mut impl DelayNs) -> Result<u8, NeotrellisError<I2C::Error>> {
+    key_event_count_write(i2c, address)?;
+    delay.delay_us(READ_DELAY_US);
+    read_byte(i2c, address)
+}
+
+fn configure_key<I2C: I2c>(i2c: &mut I2C, address: u8, key_index: usize, edges: KeyEdges, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+    if key_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+    write(i2c, address, &configure_key_command(key_index, edges, enabled))
+}
+
+fn configure_key_command(key_index: usize, edges: KeyEdges, enabled: bool) -> [u8; 4] {
+    [
+        KEYPAD_REGISTER_BASE,
+        KeypadRegister::Event as u8,
+        neo_trellis_index(key_index),
+        // The low bit enables or disables the edges given in the next four bits
+        (edges.0 << 1) | enabled as u8
+    ]
+}
+
+fn key_interrupt_command(enable: bool) -> [u8; 3] {
+    let register = if enable { KeypadRegister::InterruptEnableSet } else { KeypadRegister::InterruptEnableClear };
+    [KEYPAD_REGISTER_BASE, register as u8, 0x01]
+}
+
+/// The sizes of the reads that empty a FIFO of `count` events through a buffer of
+/// `buffer_length` bytes.
+pub(crate) fn drain_chunks(count: u8, buffer_length: usize) -> impl Iterator<Item = u8> {
+    let (count, buffer_length) = (count as usize, buffer_length.max(1));
+    (0..count).step_by(buffer_length).map(move |start| (count - start).min(buffer_length) as u8)
+}
+
+/// Splits the part of `event_buffer` that `count` events are read into from the number of
+/// events that do not fit.
+pub(crate) fn event_chunk(event_buffer: &mut [u8], count: u8) -> (&mut [u8], usize) {
+    let length = (count as usize).min(event_buffer.len());
+    (&mut event_buffer[..length], count as usize - length)
+}
+
+fn key_event_count_write<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Count as u8];
+    write(i2c, address, &command)
+}
+
+fn key_event_iterate_write<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    let command: [u8; 2] = [KEYPAD_REGISTER_BASE, KeypadRegister::Fifo as u8];
+    write(i2c, address, &command)
+}
+
+fn enable_leds<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    for command in enable_leds_commands().iter() { write(i2c, address, command)? }
+    Ok(())
+}
+
+fn enable_leds_commands() -> [&'static [u8]; 3] {
+    [
+        // Set the speed to 800khz
+        &[NEOPIXEL_REGISTER_BASE, NeopixelRegister::Speed as u8, 0x01],
+        // Update the length of the remote buffer
+        &[NEOPIXEL_REGISTER_BASE, NeopixelRegister::BufferLength as u8, 0, 16 * 3],
         // Set the pin
-        let command: [u8; 3] = [
-            NEOPIXEL_REGISTER_BASE,
-            NeopixelRegister::Pin as u8,
-            3
-        ];
-        self.i2c.write(self.address, &command)
+        &[NEOPIXEL_REGISTER_BASE, NeopixelRegister::Pin as u8, 3]
+    ]
+}
+
+fn clear_leds<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    for command in clear_leds_commands().iter() { write(i2c, address, command)? }
+    Ok(())
+}
+
+fn clear_leds_commands() -> [[u8; 8 * 3 + 4]; 2] {
+    // The SeeSaw cannot clear it all in one write; split into two writes with offset
+    let mut command = [0; 8 * 3 + 4];
+    command[0] = NEOPIXEL_REGISTER_BASE;
+    command[1] = NeopixelRegister::Buffer as u8;
+
+    let mut second_half = command;
+    second_half[3] = 24;
+    [command, second_half]
+}
+
+fn set_led<I2C: I2c>(i2c: &mut I2C, address: u8, led_index: u8, color: Color) -> Result<(), NeotrellisError<I2C::Error>> {
+    if led_index >= 16 { return Err(NeotrellisError::InvalidKey); }
+    write(i2c, address, &set_led_command(led_index, color))
+}
+
+fn set_led_command(led_index: u8, color: Color) -> [u8; 7] {
+    [
+        NEOPIXEL_REGISTER_BASE,
+        NeopixelRegister::Buffer as u8,
+        0, // Offset high
+        3 * led_index, // Offset low
+        color.g,
+        color.r,
+        color.b
+    ]
+}
+
+fn refresh_leds<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    let command: [u8; 2] = [NEOPIXEL_REGISTER_BASE, NeopixelRegister::Show as u8];
+    write(i2c, address, &command)
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+    use crate::sim::{NoDelay, SimulatedBus, SimulatedPin, Transaction};
+
+    #[test]
+    fn initialize_sets_up_keypad_and_leds() {
+        let bus = single_board_bus();
+        neotrellis(&bus);
+
+        let bus = bus.borrow();
+        let board = bus.board(DEFAULT_ADDRESS).unwrap();
+        assert_eq!(board.neopixel_pin(), Some(3));
+        assert_eq!(board.neopixel_speed(), Some(1));
+        assert_eq!(board.neopixel_buffer(), &[0; 48][..]);
+        assert_eq!(board.show_count(), 1);
+        for key in 0..16 {
+            assert!(board.is_edge_enabled(key, 2) && board.is_edge_enabled(key, 3));
+            assert!(!board.is_edge_enabled(key, 0) && !board.is_edge_enabled(key, 1));
+        }
     }
 
-    /// Sets all LEDs on the Neotrellis to an RGB value of #000000
-    pub fn clear_leds(&mut self) -> Result<(), E> {
-        // The SeeSaw cannot clear it all in one write; split into two writes with offset
-        let mut command = [0; 8 * 3 + 4];
-        command[0] = NEOPIXEL_REGISTER_BASE;
-        command[1] = NeopixelRegister::Buffer as u8;
-        self.i2c.write(self.address, &command)?;
+    #[test]
+    fn initialize_rejects_other_hardware() {
+        let mut bus = SimulatedBus::new(&[DEFAULT_ADDRESS]);
+        bus.board_mut(DEFAULT_ADDRESS).unwrap().set_hardware_id(0x87);
 
-        command[3] = 24;
-        self.i2c.write(self.address, &command)
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+        assert_eq!(nt.initialize(), Err(NeotrellisError::WrongHardwareId(0x87)));
     }
 
-    /// Sets an LED on the Neotrellis to an RGB value
-    pub fn set_led(&mut self, led_index: u8, red: u8, green: u8, blue: u8) -> Result<(), E> {
-        let command = [
-            NEOPIXEL_REGISTER_BASE,
-            NeopixelRegister::Buffer as u8,
-            0, // Offset high
-            3 * led_index, // Offset low
-            green,
-            red,
-            blue
-        ];
+    #[test]
+    fn missing_board_is_a_bus_error() {
+        let mut bus = SimulatedBus::new(&[DEFAULT_ADDRESS]);
 
-        self.i2c.write(self.address, &command)
+        let mut nt = Neotrellis::new(&mut bus, NoDelay, Some(0x30));
+        let error = NeotrellisError::Bus(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert_eq!(nt.initialize(), Err(error));
     }
 
-    /// Refreshes the LED display based on changes made in `clear_leds` and `set_led`.
-    /// Should be called 300us after these functions to allow time for the data to
-    /// latch.
-    pub fn refresh_leds(&mut self) -> Result<(), E> {
-        let command: [u8; 2] = [NEOPIXEL_REGISTER_BASE, NeopixelRegister::Show as u8];
-        self.i2c.write(self.address, &command)
+    #[test]
+    fn set_led_writes_grb_at_the_led_offset() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.set_led(5, Color::rgb(106, 13, 173)).unwrap();
+        assert_eq!(
+            bus.borrow().transactions(),
+            &[Transaction::Write(DEFAULT_ADDRESS, std::vec![0x0E, 0x04, 0, 15, 13, 106, 173])]
+        );
+
+        nt.refresh_leds().unwrap();
+        assert_eq!(bus.borrow().board(DEFAULT_ADDRESS).unwrap().pixel(5), (106, 13, 173));
+        assert_eq!(nt.set_led(16, (1, 2, 3)), Err(NeotrellisError::InvalidKey));
+    }
+
+    #[test]
+    fn key_events_are_parsed() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        {
+            let mut bus = bus.borrow_mut();
+            let board = bus.board_mut(DEFAULT_ADDRESS).unwrap();
+            board.press(6);
+            board.release(6);
+            board.press(15);
+        }
+
+        let mut raw_events = [0; 16];
+        let events: std::vec::Vec<_> = nt.key_event_iterate(&mut raw_events)
+            .unwrap()
+            .map(|event| (event.key_index, event.event_type))
+            .collect();
+
+        assert_eq!(events, [
+            (6, NeotrellisEventType::KeyPress),
+            (6, NeotrellisEventType::KeyRelease),
+            (15, NeotrellisEventType::KeyPress)
+        ]);
+        assert_eq!(nt.key_event_iterate(&mut raw_events).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn events_that_do_not_fit_are_deferred() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        for key in 0..5 { bus.borrow_mut().board_mut(DEFAULT_ADDRESS).unwrap().press(key); }
+
+        let mut raw_events = [0; 2];
+        let events = nt.key_event_iterate(&mut raw_events).unwrap();
+        assert_eq!((events.deferred(), events.dropped()), (3, 0));
+        assert_eq!(events.map(|event| event.key_index).collect::<std::vec::Vec<_>>(), [0, 1]);
+        assert_eq!(nt.key_event_iterate(&mut []).unwrap().deferred(), 3);
+
+        // The FIFO only has 3 events left, so the Seesaw pads the read with 0xFF
+        let mut raw_events = [0; 8];
+        nt.key_event_iterate_write().unwrap();
+        let events = nt.key_event_iterate_read(&mut raw_events, 4).unwrap();
+        assert_eq!((events.deferred(), events.dropped()), (0, 1));
+        assert_eq!(events.count(), 3);
+    }
+
+    #[test]
+    fn drain_reads_the_fifo_in_chunks() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        for key in 0..7 { bus.borrow_mut().board_mut(DEFAULT_ADDRESS).unwrap().press(key); }
+
+        let mut keys = std::vec::Vec::new();
+        let mut raw_events = [0; 3];
+        let report = nt.key_event_drain(&mut raw_events, |event| keys.push(event.key_index)).unwrap();
+
+        assert_eq!(report, DrainReport { events: 7, dropped: 0, reads: 3 });
+        assert_eq!(keys, [0, 1, 2, 3, 4, 5, 6]);
+        assert!(bus.borrow().board(DEFAULT_ADDRESS).unwrap().fifo().is_empty());
+        assert_eq!(nt.key_event_drain(&mut [], |_| {}), Err(NeotrellisError::BufferTooSmall));
+    }
+
+    #[test]
+    fn configure_key_changes_only_the_given_edges() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.configure_key(0, KeyEdges::HIGH, true).unwrap();
+        nt.configure_key(1, KeyEdges::ALL, false).unwrap();
+        assert_eq!(nt.configure_key(16, KeyEdges::ALL, false), Err(NeotrellisError::InvalidKey));
+
+        let mut bus = bus.borrow_mut();
+        let board = bus.board_mut(DEFAULT_ADDRESS).unwrap();
+        assert!(board.is_edge_enabled(0, 0) && board.is_edge_enabled(0, 3));
+        assert!((0..4).all(|edge| !board.is_edge_enabled(1, edge)));
+
+        board.press(0);
+        board.press(1);
+        assert_eq!(board.fifo().len(), 2);
+        assert_eq!(NeotrellisEvent::from(board.fifo()[1]).event_type, NeotrellisEventType::KeyHigh);
+    }
+
+    #[test]
+    fn interrupt_pin_gates_key_event_reads() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        let mut pin = SimulatedPin::new();
+        let mut raw_events = [0; 16];
+
+        nt.enable_key_interrupt().unwrap();
+        assert!(bus.borrow().board(DEFAULT_ADDRESS).unwrap().is_interrupt_enabled());
+        bus.borrow_mut().clear_transactions();
+
+        assert_eq!(nt.key_event_iterate_on_interrupt(&mut pin, &mut raw_events).unwrap().count(), 0);
+        assert!(bus.borrow().transactions().is_empty());
+
+        bus.borrow_mut().board_mut(DEFAULT_ADDRESS).unwrap().press(3);
+        pin.set_low(bus.borrow().interrupt_asserted());
+        assert_eq!(nt.key_event_iterate_on_interrupt(&mut pin, &mut raw_events).unwrap().count(), 1);
+        assert!(!bus.borrow().interrupt_asserted());
+    }
+
+    #[test]
+    fn flush_writes_only_the_dirty_range() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.set_pixel(2, (1, 2, 3)).unwrap();
+        nt.set_pixel(4, (4, 5, 6)).unwrap();
+        nt.flush().unwrap();
+
+        {
+            let bus = bus.borrow();
+            assert_eq!(bus.transactions()[0], Transaction::Write(
+                DEFAULT_ADDRESS,
+                std::vec![0x0E, 0x04, 0, 6, 2, 1, 3, 0, 0, 0, 5, 4, 6]
+            ));
+            assert_eq!(bus.transactions().len(), 2);
+
+            let board = bus.board(DEFAULT_ADDRESS).unwrap();
+            assert_eq!(board.pixel(2), (1, 2, 3));
+            assert_eq!(board.pixel(4), (4, 5, 6));
+        }
+
+        // Nothing has changed, so nothing is sent
+        bus.borrow_mut().clear_transactions();
+        nt.set_pixel(2, (1, 2, 3)).unwrap();
+        nt.flush().unwrap();
+        assert!(bus.borrow().transactions().is_empty());
+    }
+
+    #[test]
+    fn flush_splits_large_updates_into_chunks() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.fill((10, 20, 30));
+        assert_eq!(nt.pixel(15), Some(Color::rgb(10, 20, 30)));
+        nt.flush().unwrap();
+
+        let bus = bus.borrow();
+        assert_eq!(bus.transactions().len(), 3);
+        let board = bus.board(DEFAULT_ADDRESS).unwrap();
+        assert!((0..16).all(|led| board.pixel(led) == (10, 20, 30)));
+    }
+
+    #[test]
+    fn brightness_and_gamma_are_applied_to_everything_sent() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        nt.set_pixel(0, (200, 100, 0)).unwrap();
+        nt.flush().unwrap();
+
+        // Changing the brightness resends every pixel, scaled
+        nt.set_brightness(128);
+        nt.flush().unwrap();
+        assert_eq!(bus.borrow().board(DEFAULT_ADDRESS).unwrap().pixel(0), (100, 50, 0));
+        assert_eq!(nt.pixel(0), Some(Color::rgb(200, 100, 0)));
+
+        nt.set_gamma(Some(&GAMMA_2_8));
+        nt.set_led(1, Color::WHITE).unwrap();
+        nt.flush().unwrap();
+        let bus = bus.borrow();
+        let board = bus.board(DEFAULT_ADDRESS).unwrap();
+        assert_eq!(board.buffered_pixel(0), (GAMMA_2_8[100], GAMMA_2_8[50], 0));
+        assert_eq!(board.buffered_pixel(1), (37, 37, 37));
     }
 }
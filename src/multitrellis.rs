@@ -0,0 +1,405 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::InputPin;
+use embedded_hal::i2c::I2c;
+
+use crate::color::ColorCorrection;
+use crate::seesaw::{read, write, READ_DELAY_US};
+use crate::{
+    is_key_event,
+    Color,
+    DrainReport,
+    KeyEdges,
+    NeotrellisError,
+    NeotrellisEvent,
+    NeotrellisEventIterator,
+    NeotrellisEventType
+};
+
+/// Structure used for interfacing with several Neotrellis boards tiled into one
+/// larger key grid on a single I2C bus.
+///
+/// The boards are given as a `ROWS` x `COLS` array of I2C addresses laid out the same
+/// way as the physical boards, so `addresses[0][0]` is the top left board and
+/// `addresses[0][1]` is the board to its right. Each board needs a unique address,
+/// which is set with the address jumpers on the back of the board.
+///
+/// Keys and LEDs are addressed in global (x, y) coordinates, where x is in
+/// `0..COLS * 4` and y is in `0..ROWS * 4`.
+///
+/// Example usage: a 2x2 grid of boards turning an LED purple while it is pressed:
+/// ```ignore
+/// let mut mt = MultiTrellis::new(i2c, delay, [[0x2E, 0x2F], [0x30, 0x31]]);
+/// mt.initialize().unwrap();
+///
+/// loop {
+///     mt.delay.delay_us(20_000);
+///
+///     let mut raw_events: [u8; 64] = [0; 64];
+///     for event in mt.key_event_iterate(&mut raw_events).unwrap() {
+///         match event.event_type {
+///             NeotrellisEventType::KeyPress =>
+///                 mt.set_led(event.x, event.y, Color::PURPLE).unwrap(),
+///             NeotrellisEventType::KeyRelease =>
+///                 mt.set_led(event.x, event.y, Color::BLACK).unwrap(),
+///             _ => {}
+///         };
+///     }
+///
+///     mt.delay.delay_us(300);
+///     mt.refresh_leds().unwrap();
+/// }
+/// ```
+pub struct MultiTrellis<I2C, D, const ROWS: usize, const COLS: usize> {
+    i2c: I2C,
+    addresses: [[u8; COLS]; ROWS],
+    correction: ColorCorrection,
+
+    /// The delay provider used to meet the Seesaw's timing requirements.
+    pub delay: D
+}
+
+/// A structure that represents a keypad event on a `MultiTrellis` grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiTrellisEvent {
+    /// The global column of the key that generated the event.
+    pub x: usize,
+
+    /// The global row of the key that generated the event.
+    pub y: usize,
+
+    /// The type of the event; KeyPress or KeyRelease unless other edges have been
+    /// enabled with `configure_key`.
+    pub event_type: NeotrellisEventType
+}
+
+impl MultiTrellisEvent {
+    /// The event of the board in the given row and column of the grid, in global coordinates.
+    fn on_board(event: NeotrellisEvent, row: usize, col: usize) -> Self {
+        MultiTrellisEvent {
+            x: col * 4 + event.key_index % 4,
+            y: row * 4 + event.key_index / 4,
+            event_type: event.event_type
+        }
+    }
+}
+
+/// Iterator returned by the `key_event_iterate` function of the MultiTrellis struct.
+/// Iterates through the raw bytes read from each board's event FIFO and turns them
+/// into MultiTrellisEvents, skipping bytes that are not key events like
+/// `NeotrellisEventIterator` does.
+pub struct MultiTrellisEventIterator<'a, const ROWS: usize, const COLS: usize> {
+    index: usize,
+    next_board: usize,
+    row: usize,
+    col: usize,
+    remaining: usize,
+    counts: [[u8; COLS]; ROWS],
+    raw_events: &'a [u8],
+    deferred: usize
+}
+
+impl <'a, const ROWS: usize, const COLS: usize> MultiTrellisEventIterator<'a, ROWS, COLS> {
+    fn new(counts: [[u8; COLS]; ROWS], raw_events: &'a [u8], deferred: usize) -> Self {
+        MultiTrellisEventIterator { index: 0, next_board: 0, row: 0, col: 0, remaining: 0, counts, raw_events, deferred }
+    }
+
+    /// The number of raw bytes read from the FIFOs that were not key events and are skipped.
+    pub fn dropped(&self) -> usize {
+        self.raw_events.iter().filter(|&&raw| !is_key_event(raw)).count()
+    }
+
+    /// The number of events that did not fit in the event buffer. They are left in the
+    /// FIFOs of their boards and returned by the next read.
+    pub fn deferred(&self) -> usize {
+        self.deferred
+    }
+}
+
+impl <'a, const ROWS: usize, const COLS: usize> Iterator for MultiTrellisEventIterator<'a, ROWS, COLS> {
+    type Item = MultiTrellisEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Move on to the next board that has events
+            while self.remaining == 0 {
+                if self.next_board == ROWS * COLS { return None; }
+
+                self.row = self.next_board / COLS;
+                self.col = self.next_board % COLS;
+                self.remaining = self.counts[self.row][self.col] as usize;
+                self.next_board += 1;
+            }
+
+            let raw = self.raw_events[self.index];
+            self.index += 1;
+            self.remaining -= 1;
+            if !is_key_event(raw) { continue; }
+
+            return Some(MultiTrellisEvent::on_board(NeotrellisEvent::from(raw), self.row, self.col));
+        }
+    }
+}
+
+impl <I2C, D, const ROWS: usize, const COLS: usize> MultiTrellis<I2C, D, ROWS, COLS> {
+    /// The number of key columns in the grid.
+    pub const WIDTH: usize = COLS * 4;
+
+    /// The number of key rows in the grid.
+    pub const HEIGHT: usize = ROWS * 4;
+}
+
+impl <I2C: I2c, D: DelayNs, const ROWS: usize, const COLS: usize> MultiTrellis<I2C, D, ROWS, COLS> {
+    /// Creates a new MultiTrellis struct which can be used to communicate with a grid of
+    /// Neotrellis boards sharing the `i2c` bus.
+    ///
+    /// The `delay` parameter has the same meaning as in `Neotrellis::new`. The
+    /// `addresses` parameter holds the I2C address of every board, laid out in the same
+    /// rows and columns as the boards themselves.
+    pub fn new(i2c: I2C, delay: D, addresses: [[u8; COLS]; ROWS]) -> Self {
+        MultiTrellis { i2c, addresses, correction: ColorCorrection::NONE, delay }
+    }
+
+    /// Consumes the MultiTrellis and hands back the I2C bus and delay it was created with.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// Initializes every board in the grid in the same way as `Neotrellis::initialize`.
+    pub fn initialize(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for &address in self.addresses.iter().flatten() {
+            crate::initialize(&mut self.i2c, address, &mut self.delay)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delay-based write/read operation that reads the pending events of every board in
+    /// the grid into `event_buffer` and returns an iterator over them in global coordinates.
+    ///
+    /// Boards are read row by row. Once `event_buffer` is full, the remaining events are
+    /// left unread on their boards and counted by the iterator's `deferred`.
+    pub fn key_event_iterate<'a>(&mut self, event_buffer: &'a mut [u8]) -> Result<MultiTrellisEventIterator<'a, ROWS, COLS>, NeotrellisError<I2C::Error>> {
+        let mut counts = [[0; COLS]; ROWS];
+        let mut filled = 0;
+        let mut deferred = 0;
+
+        for (row, addresses) in self.addresses.iter().enumerate() {
+            for (col, &address) in addresses.iter().enumerate() {
+                let available = event_buffer.len() - filled;
+                let pending = crate::key_event_count(&mut self.i2c, address, &mut self.delay)? as usize;
+                let count = pending.min(available);
+                deferred += pending - count;
+                if count == 0 { continue; }
+
+                crate::key_event_iterate_write(&mut self.i2c, address)?;
+                self.delay.delay_us(READ_DELAY_US);
+                read(&mut self.i2c, address, &mut event_buffer[filled..filled + count])?;
+
+                counts[row][col] = count as u8;
+                filled += count;
+            }
+        }
+
+        Ok(MultiTrellisEventIterator::new(counts, &event_buffer[..filled], deferred))
+    }
+
+    /// Delay-based write/read operation that empties the keypad FIFO of every board in the
+    /// grid, like `Neotrellis::key_event_drain`. Boards are read row by row, each in chunks
+    /// the size of `event_buffer`, and every event is handed to `on_event` in global
+    /// coordinates. Returns `NeotrellisError::BufferTooSmall` if `event_buffer` is empty.
+    pub fn key_event_drain<F: FnMut(MultiTrellisEvent)>(&mut self, event_buffer: &mut [u8], mut on_event: F) -> Result<DrainReport, NeotrellisError<I2C::Error>> {
+        if event_buffer.is_empty() { return Err(NeotrellisError::BufferTooSmall); }
+
+        let mut report = DrainReport::default();
+        for (row, addresses) in self.addresses.iter().enumerate() {
+            for (col, &address) in addresses.iter().enumerate() {
+                let pending = crate::key_event_count(&mut self.i2c, address, &mut self.delay)?;
+                for count in crate::drain_chunks(pending, event_buffer.len()) {
+                    crate::key_event_iterate_write(&mut self.i2c, address)?;
+                    self.delay.delay_us(READ_DELAY_US);
+                    let (raw_events, _) = crate::event_chunk(event_buffer, count);
+                    read(&mut self.i2c, address, raw_events)?;
+
+                    let events = NeotrellisEventIterator::new(raw_events, 0);
+                    report.record(events.dropped(), events.map(|event| MultiTrellisEvent::on_board(event, row, col)), &mut on_event);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Delay-based write/read operation like `key_event_iterate` that only talks to the
+    /// boards if `interrupt_pin` is low. The INT lines of the boards are open drain, so
+    /// they can all be wired to this one pin.
+    ///
+    /// The keypad interrupts must have been enabled with `enable_key_interrupt` first.
+    pub fn key_event_iterate_on_interrupt<'a, P: InputPin>(&mut self, interrupt_pin: &mut P, event_buffer: &'a mut [u8]) -> Result<MultiTrellisEventIterator<'a, ROWS, COLS>, NeotrellisError<I2C::Error>> {
+        if !interrupt_pin.is_low().map_err(|_| NeotrellisError::Pin)? {
+            return Ok(MultiTrellisEventIterator::new([[0; COLS]; ROWS], &event_buffer[..0], 0));
+        }
+
+        self.key_event_iterate(event_buffer)
+    }
+
+    /// Enables or disables events for the given `edges` of the key at global coordinates
+    /// (x, y), in the same way as `Neotrellis::configure_key`. Returns
+    /// `NeotrellisError::InvalidKey` if the coordinates are outside of the grid.
+    pub fn configure_key(&mut self, x: usize, y: usize, edges: KeyEdges, enabled: bool) -> Result<(), NeotrellisError<I2C::Error>> {
+        if x >= Self::WIDTH || y >= Self::HEIGHT { return Err(NeotrellisError::InvalidKey); }
+
+        let address = self.addresses[y / 4][x / 4];
+        crate::configure_key(&mut self.i2c, address, (y % 4) * 4 + x % 4, edges, enabled)
+    }
+
+    /// Makes every board pull its INT line low while there are events in its keypad FIFO.
+    pub fn enable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for &address in self.addresses.iter().flatten() {
+            write(&mut self.i2c, address, &crate::key_interrupt_command(true))?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops every board from pulling its INT line low for keypad events.
+    pub fn disable_key_interrupt(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for &address in self.addresses.iter().flatten() {
+            write(&mut self.i2c, address, &crate::key_interrupt_command(false))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets all LEDs on every board to an RGB value of #000000
+    pub fn clear_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for &address in self.addresses.iter().flatten() {
+            crate::clear_leds(&mut self.i2c, address)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the LED at global coordinates (x, y) to a color. Returns
+    /// `NeotrellisError::InvalidKey` if the coordinates are outside of the grid.
+    pub fn set_led(&mut self, x: usize, y: usize, color: impl Into<Color>) -> Result<(), NeotrellisError<I2C::Error>> {
+        if x >= Self::WIDTH || y >= Self::HEIGHT { return Err(NeotrellisError::InvalidKey); }
+
+        let address = self.addresses[y / 4][x / 4];
+        let led_index = ((y % 4) * 4 + x % 4) as u8;
+
+        crate::set_led(&mut self.i2c, address, led_index, self.correction.apply(color.into()))
+    }
+
+    /// Sets the LEDs of the whole grid from `colors`, indexed `y * WIDTH + x`, with
+    /// `set_led`, e.g. from a frame rendered by an effect of the `animation` module. LEDs
+    /// past the end of `colors` are left as they are.
+    pub fn set_leds(&mut self, colors: &[Color]) -> Result<(), NeotrellisError<I2C::Error>> {
+        for (index, &color) in colors.iter().take(Self::WIDTH * Self::HEIGHT).enumerate() {
+            self.set_led(index % Self::WIDTH, index / Self::WIDTH, color)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the brightness every color is scaled by in `set_led`, from 0 (off) to 255 (full,
+    /// the default).
+    ///
+    /// The driver keeps no copy of the LEDs, so LEDs that are already lit keep their old
+    /// brightness until they are sent again. Call `set_leds` with the current frame to
+    /// apply the new brightness to the whole grid.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.correction.brightness = brightness;
+    }
+
+    /// The brightness set with `set_brightness`.
+    pub fn brightness(&self) -> u8 {
+        self.correction.brightness
+    }
+
+    /// Sets the gamma correction lookup table applied to each color component in `set_led`
+    /// after the brightness, such as `GAMMA_2_8`, or `None` to send the components as they are.
+    /// Like the brightness, it only applies to LEDs sent after it is changed.
+    pub fn set_gamma(&mut self, table: Option<&'static [u8; 256]>) {
+        self.correction.gamma = table;
+    }
+
+    /// Refreshes the LED display of every board. As with `Neotrellis::refresh_leds`, this
+    /// should be called 300us after the LEDs were last changed.
+    pub fn refresh_leds(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        for &address in self.addresses.iter().flatten() {
+            crate::refresh_leds(&mut self.i2c, address)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{multi_board_bus, multitrellis};
+
+    const ADDRESSES: [[u8; 2]; 2] = [[0x2E, 0x2F], [0x30, 0x31]];
+
+    #[test]
+    fn set_led_maps_global_coordinates_to_boards() {
+        let bus = multi_board_bus(ADDRESSES);
+        let mut mt = multitrellis(&bus, ADDRESSES);
+
+        mt.set_led(5, 6, (1, 2, 3)).unwrap();
+        mt.refresh_leds().unwrap();
+        assert_eq!(bus.borrow().board(0x31).unwrap().pixel(9), (1, 2, 3));
+        assert_eq!(mt.set_led(8, 0, (1, 2, 3)), Err(NeotrellisError::InvalidKey));
+    }
+
+    #[test]
+    fn key_events_are_reported_in_global_coordinates() {
+        let bus = multi_board_bus(ADDRESSES);
+        let mut mt = multitrellis(&bus, ADDRESSES);
+
+        bus.borrow_mut().board_mut(0x2F).unwrap().press(4);
+        bus.borrow_mut().board_mut(0x30).unwrap().press(15);
+        bus.borrow_mut().board_mut(0x30).unwrap().release(15);
+
+        let mut raw_events = [0; 16];
+        let events: std::vec::Vec<_> = mt.key_event_iterate(&mut raw_events).unwrap().collect();
+        assert_eq!(events, [
+            MultiTrellisEvent { x: 4, y: 1, event_type: NeotrellisEventType::KeyPress },
+            MultiTrellisEvent { x: 3, y: 7, event_type: NeotrellisEventType::KeyPress },
+            MultiTrellisEvent { x: 3, y: 7, event_type: NeotrellisEventType::KeyRelease }
+        ]);
+    }
+
+    #[test]
+    fn events_that_do_not_fit_are_left_on_the_board() {
+        let bus = multi_board_bus(ADDRESSES);
+        let mut mt = multitrellis(&bus, ADDRESSES);
+
+        bus.borrow_mut().board_mut(0x2E).unwrap().press(0);
+        bus.borrow_mut().board_mut(0x31).unwrap().press(0);
+
+        let mut raw_events = [0; 1];
+        let events = mt.key_event_iterate(&mut raw_events).unwrap();
+        assert_eq!(events.deferred(), 1);
+        assert_eq!(events.count(), 1);
+        assert_eq!(bus.borrow().board(0x31).unwrap().fifo().len(), 1);
+    }
+
+    #[test]
+    fn drain_empties_every_board_in_chunks() {
+        let bus = multi_board_bus(ADDRESSES);
+        let mut mt = multitrellis(&bus, ADDRESSES);
+
+        for key in 0..3 { bus.borrow_mut().board_mut(0x2F).unwrap().press(key); }
+        bus.borrow_mut().board_mut(0x30).unwrap().press(15);
+
+        let mut events = std::vec::Vec::new();
+        let mut raw_events = [0; 2];
+        let report = mt.key_event_drain(&mut raw_events, |event| events.push((event.x, event.y))).unwrap();
+
+        assert_eq!(report, DrainReport { events: 4, dropped: 0, reads: 3 });
+        assert_eq!(events, [(4, 0), (5, 0), (6, 0), (3, 7)]);
+        assert!(ADDRESSES.iter().flatten().all(|&address| bus.borrow().board(address).unwrap().fifo().is_empty()));
+        assert_eq!(mt.key_event_drain(&mut [], |_| {}), Err(NeotrellisError::BufferTooSmall));
+    }
+}
@@ -0,0 +1,172 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::InputPin;
+use embedded_hal::i2c::I2c;
+
+use crate::seesaw::READ_DELAY_US;
+use crate::{Neotrellis, NeotrellisError, NeotrellisEventIterator};
+
+enum PollState {
+    /// No request is pending; the next poll requests the event count.
+    Idle,
+
+    /// The event count was requested at the given time.
+    CountRequested(u32),
+
+    /// The given number of events was requested from the FIFO at the given time.
+    FifoRequested(u32, u8)
+}
+
+/// A non-blocking state machine for reading keypad events.
+///
+/// Reading events takes a count request, a count read, a FIFO request and a FIFO read,
+/// with `READ_DELAY_US` between each request and its read. Each call to `poll` makes
+/// whichever of these steps is due at the given time and returns straight away, so the
+/// keypad can be read from a busy main loop without ever waiting on the board.
+///
+/// While a request is pending (`is_pending` returns true), any other transaction with the
+/// board changes the register that the next read returns. Make other transactions, such
+/// as LED updates, while the poller is idle, or call `cancel` after making them.
+///
+/// Example usage:
+/// ```ignore
+/// let mut poller = KeyEventPoller::new();
+/// let mut raw_events: [u8; 16] = [0; 16];
+///
+/// loop {
+///     for event in poller.poll(&mut nt, timer.now_us(), &mut raw_events).unwrap() {
+///         handle(event);
+///     }
+///
+///     other_work();
+/// }
+/// ```
+pub struct KeyEventPoller {
+    state: PollState
+}
+
+impl KeyEventPoller {
+    /// Creates a new poller with no request pending.
+    pub fn new() -> Self {
+        KeyEventPoller { state: PollState::Idle }
+    }
+
+    /// Returns true if a request has been made to the board and its read is still to come.
+    pub fn is_pending(&self) -> bool {
+        !matches!(self.state, PollState::Idle)
+    }
+
+    /// Abandons any pending request, so that the next poll starts again with a count request.
+    pub fn cancel(&mut self) {
+        self.state = PollState::Idle;
+    }
+
+    /// Advances the state machine if its next step is due at `now_us`, a free-running
+    /// microsecond timestamp that may wrap around.
+    ///
+    /// Returns an iterator over the events read into `event_buffer` when the FIFO read
+    /// step is made, and an empty iterator otherwise.
+    pub fn poll<'a, I2C: I2c, D: DelayNs>(
+        &mut self,
+        neotrellis: &mut Neotrellis<I2C, D>,
+        now_us: u32,
+        event_buffer: &'a mut [u8]
+    ) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        match self.state {
+            PollState::Idle => {
+                neotrellis.key_event_count_write()?;
+                self.state = PollState::CountRequested(now_us);
+            },
+            PollState::CountRequested(requested_at) if now_us.wrapping_sub(requested_at) >= READ_DELAY_US => {
+                self.state = PollState::Idle;
+
+                let count = neotrellis.key_event_count_read()?;
+                if count > 0 {
+                    neotrellis.key_event_iterate_write()?;
+                    self.state = PollState::FifoRequested(now_us, count);
+                }
+            },
+            PollState::FifoRequested(requested_at, count) if now_us.wrapping_sub(requested_at) >= READ_DELAY_US => {
+                self.state = PollState::Idle;
+                return neotrellis.key_event_iterate_read(event_buffer, count);
+            },
+            _ => {}
+        }
+
+        Ok(NeotrellisEventIterator::empty())
+    }
+
+    /// Like `poll`, but a new count request is only made while `interrupt_pin`, connected
+    /// to the Neotrellis INT line, is low. Requests that are already pending carry on
+    /// regardless of the pin.
+    ///
+    /// The keypad interrupt must have been enabled with `Neotrellis::enable_key_interrupt` first.
+    pub fn poll_on_interrupt<'a, I2C: I2c, D: DelayNs, P: InputPin>(
+        &mut self,
+        neotrellis: &mut Neotrellis<I2C, D>,
+        interrupt_pin: &mut P,
+        now_us: u32,
+        event_buffer: &'a mut [u8]
+    ) -> Result<NeotrellisEventIterator<'a>, NeotrellisError<I2C::Error>> {
+        if !self.is_pending() && !interrupt_pin.is_low().map_err(|_| NeotrellisError::Pin)? {
+            return Ok(NeotrellisEventIterator::empty());
+        }
+
+        self.poll(neotrellis, now_us, event_buffer)
+    }
+}
+
+impl Default for KeyEventPoller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+    use crate::sim::Transaction;
+    use crate::NeotrellisEventType;
+
+    #[test]
+    fn poll_waits_for_the_settle_time_between_steps() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        bus.borrow_mut().board_mut(0x2E).unwrap().press(9);
+        bus.borrow_mut().clear_transactions();
+
+        let mut poller = KeyEventPoller::new();
+        let mut raw_events = [0; 16];
+
+        // Count request
+        assert_eq!(poller.poll(&mut nt, u32::MAX - 100, &mut raw_events).unwrap().count(), 0);
+        assert!(poller.is_pending());
+
+        // Too early for the count read, even across the timestamp wrapping
+        assert_eq!(poller.poll(&mut nt, 200, &mut raw_events).unwrap().count(), 0);
+        assert_eq!(bus.borrow().transactions().len(), 1);
+
+        // Count read and FIFO request
+        assert_eq!(poller.poll(&mut nt, 400, &mut raw_events).unwrap().count(), 0);
+        assert!(matches!(bus.borrow().transactions()[1], Transaction::Read(0x2E, ref bytes) if bytes[..] == [1]));
+
+        // FIFO read
+        assert_eq!(poller.poll(&mut nt, 600, &mut raw_events).unwrap().count(), 0);
+        let events: std::vec::Vec<_> = poller.poll(&mut nt, 900, &mut raw_events).unwrap().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].key_index, events[0].event_type), (9, NeotrellisEventType::KeyPress));
+        assert!(!poller.is_pending());
+    }
+
+    #[test]
+    fn poll_goes_idle_when_there_are_no_events() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        let mut poller = KeyEventPoller::new();
+        let mut raw_events = [0; 16];
+        poller.poll(&mut nt, 0, &mut raw_events).unwrap();
+        poller.poll(&mut nt, 500, &mut raw_events).unwrap();
+        assert!(!poller.is_pending());
+    }
+}
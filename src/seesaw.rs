@@ -0,0 +1,304 @@
+//! The generic Adafruit Seesaw core that the NeoTrellis driver is built on.
+//!
+//! Every Seesaw product (NeoTrellis, rotary encoder, gamepad, soil sensor, ...) talks the
+//! same protocol: a write of a module base, a register and optional data, and for reads, a
+//! delay followed by a read of the register's data. `Seesaw` implements that protocol along
+//! with the status and GPIO modules that every board has, and the register maps below
+//! describe the other modules so that drivers for other products can be layered on top of
+//! it the way `Neotrellis` is.
+//!
+//! Example usage: reading the position of a Seesaw rotary encoder:
+//! ```ignore
+//! let mut encoder = Seesaw::new(i2c, delay, 0x36);
+//! assert!(encoder.options().unwrap().contains(Options::ENCODER));
+//!
+//! let mut position = [0; 4];
+//! encoder.read_register(ENCODER_REGISTER_BASE, EncoderRegister::Position as u8, &mut position).unwrap();
+//! let position = i32::from_be_bytes(position);
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::NeotrellisError;
+
+/// The base of the status module: hardware ID, version, options, temperature and reset.
+pub const STATUS_REGISTER_BASE: u8 = 0x00;
+
+/// The base of the GPIO module.
+pub const GPIO_REGISTER_BASE: u8 = 0x01;
+
+/// The base of the analog input module.
+pub const ADC_REGISTER_BASE: u8 = 0x09;
+
+/// The base of the NeoPixel module.
+pub const NEOPIXEL_REGISTER_BASE: u8 = 0x0E;
+
+/// The base of the capacitive touch module.
+pub const TOUCH_REGISTER_BASE: u8 = 0x0F;
+
+/// The base of the keypad module.
+pub const KEYPAD_REGISTER_BASE: u8 = 0x10;
+
+/// The base of the rotary encoder module.
+pub const ENCODER_REGISTER_BASE: u8 = 0x11;
+
+/// The largest write the Seesaw accepts in one transaction, including the base and register.
+pub const MAX_WRITE_LENGTH: usize = 32;
+
+/// The time the Seesaw needs between a register write and the read of its data.
+pub const READ_DELAY_US: u32 = 500;
+
+/// Registers of the status module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusRegister {
+    /// 1 byte hardware ID; 0x55 for the SAMD09 boards such as the NeoTrellis.
+    HardwareId = 0x01,
+
+    /// 4 byte product code and firmware date code.
+    Version = 0x02,
+
+    /// 4 byte mask of the modules compiled into the firmware.
+    Options = 0x03,
+
+    /// 4 byte chip temperature in 16.16 fixed point degrees Celsius.
+    Temperature = 0x04,
+
+    /// Write 0xFF to restart the firmware.
+    SoftwareReset = 0x7F
+}
+
+/// Registers of the GPIO module. Each takes or gives a 4 byte pin mask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpioRegister {
+    DirectionSet = 0x02,
+    DirectionClear = 0x03,
+    Bulk = 0x04,
+    BulkSet = 0x05,
+    BulkClear = 0x06,
+    BulkToggle = 0x07,
+    InterruptEnableSet = 0x08,
+    InterruptEnableClear = 0x09,
+    InterruptFlag = 0x0A,
+    PullEnableSet = 0x0B,
+    PullEnableClear = 0x0C
+}
+
+/// Registers of the analog input module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdcRegister {
+    Status = 0x00,
+    InterruptEnable = 0x02,
+    InterruptEnableClear = 0x03,
+    WindowMode = 0x04,
+    WindowThreshold = 0x05,
+
+    /// The 2 byte reading of channel n is at this register plus n.
+    ChannelOffset = 0x07
+}
+
+/// Registers of the NeoPixel module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeopixelRegister {
+    Pin = 0x01,
+    Speed = 0x02,
+    BufferLength = 0x03,
+
+    /// A 2 byte offset followed by the pixel data to write there.
+    Buffer = 0x04,
+    Show = 0x05
+}
+
+/// Registers of the capacitive touch module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchRegister {
+    /// The 2 byte reading of channel n is at this register plus n.
+    ChannelOffset = 0x10
+}
+
+/// Registers of the keypad module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeypadRegister {
+    Status = 0x00,
+
+    /// A key number followed by its edge configuration.
+    Event = 0x01,
+    InterruptEnableSet = 0x02,
+    InterruptEnableClear = 0x03,
+    Count = 0x04,
+    Fifo = 0x10
+}
+
+/// Registers of the rotary encoder module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncoderRegister {
+    Status = 0x00,
+    InterruptEnableSet = 0x10,
+    InterruptEnableClear = 0x20,
+
+    /// 4 byte signed position.
+    Position = 0x30,
+
+    /// 4 byte signed change in position since the last read.
+    Delta = 0x40
+}
+
+/// A generic Seesaw board: the register protocol, plus the status and GPIO modules.
+///
+/// The status functions (`hardware_id`, `firmware_version`, `options`, `temperature_c`,
+/// `software_reset`) and GPIO functions (`pin_mode`, `digital_read`, `digital_write`, ...)
+/// are available on every Seesaw, including through a `Neotrellis`.
+pub struct Seesaw<I2C, D> {
+    pub(crate) i2c: I2C,
+    pub(crate) address: u8,
+
+    /// The delay provider used to meet the Seesaw's timing requirements. It is public
+    /// so that it can also be used between the independent write and read functions.
+    pub delay: D
+}
+
+impl <I2C: I2c, D: DelayNs> Seesaw<I2C, D> {
+    /// Creates a Seesaw at `address`. Nothing is sent to the board.
+    pub fn new(i2c: I2C, delay: D, address: u8) -> Self {
+        Seesaw { i2c, address, delay }
+    }
+
+    /// Consumes the Seesaw and hands back the I2C bus and delay it was created with.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// The I2C address of the board.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Delay-based function that reads the hardware ID of the board.
+    pub fn hardware_id(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        hardware_id(&mut self.i2c, self.address, &mut self.delay)
+    }
+
+    /// The `write` part of the `hardware_id` function.
+    pub fn hardware_id_write(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        hardware_id_write(&mut self.i2c, self.address)
+    }
+
+    /// The `read` operation corresponding to the `hardware_id_write` function.
+    pub fn hardware_id_read(&mut self) -> Result<u8, NeotrellisError<I2C::Error>> {
+        read_byte(&mut self.i2c, self.address)
+    }
+
+    /// Writes `data` to a register of a module. Returns `NeotrellisError::WriteTooLong`
+    /// if the write would be longer than `MAX_WRITE_LENGTH`.
+    pub fn write_register(&mut self, base: u8, register: u8, data: &[u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        let mut command = [0; MAX_WRITE_LENGTH];
+        let length = data.len() + 2;
+        if length > MAX_WRITE_LENGTH { return Err(NeotrellisError::WriteTooLong); }
+
+        command[0] = base;
+        command[1] = register;
+        command[2..length].copy_from_slice(data);
+        write(&mut self.i2c, self.address, &command[..length])
+    }
+
+    /// Delay-based write/read operation that fills `buffer` with the data of a register.
+    pub fn read_register(&mut self, base: u8, register: u8, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        self.select_register(base, register)?;
+        self.delay.delay_us(READ_DELAY_US);
+        self.read_selected(buffer)
+    }
+
+    /// The write part of the `read_register` function, which sets the register that the
+    /// next read returns.
+    pub fn select_register(&mut self, base: u8, register: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &[base, register])
+    }
+
+    /// The read operation corresponding to `select_register`.
+    pub fn read_selected(&mut self, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+        read(&mut self.i2c, self.address, buffer)
+    }
+}
+
+/// A driver built on a `Seesaw` core, such as `Neotrellis`. Lets helpers such as
+/// `GpioPin` work with any Seesaw product.
+pub trait SeesawDevice {
+    /// The I2C bus of the board.
+    type I2c: I2c;
+
+    /// The delay provider of the board.
+    type Delay: DelayNs;
+
+    /// The Seesaw core of the driver.
+    fn seesaw(&mut self) -> &mut Seesaw<Self::I2c, Self::Delay>;
+}
+
+impl <I2C: I2c, D: DelayNs> SeesawDevice for Seesaw<I2C, D> {
+    type I2c = I2C;
+    type Delay = D;
+
+    fn seesaw(&mut self) -> &mut Seesaw<I2C, D> {
+        self
+    }
+}
+
+// The bus operations below take the bus and board address explicitly so that they can
+// be shared by every driver built on the Seesaw protocol.
+
+pub(crate) fn write<I2C: I2c>(i2c: &mut I2C, address: u8, bytes: &[u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+    i2c.write(address, bytes).map_err(NeotrellisError::Bus)
+}
+
+pub(crate) fn read<I2C: I2c>(i2c: &mut I2C, address: u8, buffer: &mut [u8]) -> Result<(), NeotrellisError<I2C::Error>> {
+    i2c.read(address, buffer).map_err(NeotrellisError::Bus)
+}
+
+pub(crate) fn read_byte<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<u8, NeotrellisError<I2C::Error>> {
+    let mut read_buffer: [u8; 1] = [0; 1];
+    read(i2c, address, &mut read_buffer).map(|_| read_buffer[0])
+}
+
+pub(crate) fn read_u32<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<u32, NeotrellisError<I2C::Error>> {
+    let mut bytes = [0; 4];
+    read(i2c, address, &mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+pub(crate) fn hardware_id<I2C: I2c>(i2c: &mut I2C, address: u8, delay: &mut impl DelayNs) -> Result<u8, NeotrellisError<I2C::Error>> {
+    hardware_id_write(i2c, address)?;
+    delay.delay_us(READ_DELAY_US);
+    read_byte(i2c, address)
+}
+
+pub(crate) fn hardware_id_write<I2C: I2c>(i2c: &mut I2C, address: u8) -> Result<(), NeotrellisError<I2C::Error>> {
+    write(i2c, address, &[STATUS_REGISTER_BASE, StatusRegister::HardwareId as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_bus::i2c::RefCellDevice;
+    use crate::sim::fixtures::single_board_bus;
+    use crate::sim::{NoDelay, Transaction};
+
+    #[test]
+    fn register_reads_and_writes() {
+        let bus = single_board_bus();
+        let mut seesaw = Seesaw::new(RefCellDevice::new(&bus), NoDelay, 0x2E);
+
+        assert_eq!(seesaw.hardware_id(), Ok(0x55));
+
+        seesaw.write_register(NEOPIXEL_REGISTER_BASE, NeopixelRegister::Pin as u8, &[3]).unwrap();
+        assert_eq!(bus.borrow().board(0x2E).unwrap().neopixel_pin(), Some(3));
+        assert_eq!(seesaw.write_register(NEOPIXEL_REGISTER_BASE, NeopixelRegister::Buffer as u8, &[0; 31]), Err(NeotrellisError::WriteTooLong));
+
+        bus.borrow_mut().clear_transactions();
+        let mut count = [0xAA];
+        seesaw.read_register(KEYPAD_REGISTER_BASE, KeypadRegister::Count as u8, &mut count).unwrap();
+        assert_eq!(count, [0]);
+        assert_eq!(bus.borrow().transactions(), &[
+            Transaction::Write(0x2E, std::vec![KEYPAD_REGISTER_BASE, KeypadRegister::Count as u8]),
+            Transaction::Read(0x2E, std::vec![0])
+        ]);
+    }
+}
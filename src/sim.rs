@@ -0,0 +1,620 @@
+//! A simulated Seesaw I2C bus for testing on a host machine.
+//!
+//! `SimulatedBus` implements the embedded-hal `I2c` trait (and the `embedded-hal-async`
+//! one with the `async` feature) and answers like one or more NeoTrellis boards would.
+//! Each `SimulatedNeotrellis` on the bus models the parts of the Seesaw that the drivers
+//! use: the status registers, the GPIO pins, the keypad event configuration, FIFO and
+//! interrupt, and the NeoPixel buffer. Every transaction is recorded so that tests can
+//! check exactly what was sent.
+//!
+//! This module requires the `std` feature.
+//!
+//! Example usage: checking that a key press lights an LED:
+//! ```ignore
+//! let mut bus = SimulatedBus::new(&[0x2E]);
+//! let mut nt = Neotrellis::new(&mut bus, NoDelay, None);
+//! nt.initialize().unwrap();
+//! ...
+//! drop(nt);
+//!
+//! bus.board_mut(0x2E).unwrap().press(5);
+//! ```
+//!
+//! The simulation does not keep track of time, so the delays the drivers make between
+//! writes and reads are not checked.
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+use crate::seesaw::{
+    GpioRegister,
+    KeypadRegister,
+    NeopixelRegister,
+    StatusRegister,
+    GPIO_REGISTER_BASE,
+    KEYPAD_REGISTER_BASE,
+    NEOPIXEL_REGISTER_BASE,
+    STATUS_REGISTER_BASE
+};
+use crate::{neo_trellis_index, see_saw_index, HARDWARE_ID};
+
+const STATUS_HARDWARE_ID: u8 = StatusRegister::HardwareId as u8;
+const STATUS_VERSION: u8 = StatusRegister::Version as u8;
+const STATUS_OPTIONS: u8 = StatusRegister::Options as u8;
+const STATUS_TEMPERATURE: u8 = StatusRegister::Temperature as u8;
+const STATUS_SOFTWARE_RESET: u8 = StatusRegister::SoftwareReset as u8;
+
+const GPIO_DIRECTION_SET: u8 = GpioRegister::DirectionSet as u8;
+const GPIO_DIRECTION_CLEAR: u8 = GpioRegister::DirectionClear as u8;
+const GPIO_BULK: u8 = GpioRegister::Bulk as u8;
+const GPIO_BULK_SET: u8 = GpioRegister::BulkSet as u8;
+const GPIO_BULK_CLEAR: u8 = GpioRegister::BulkClear as u8;
+const GPIO_BULK_TOGGLE: u8 = GpioRegister::BulkToggle as u8;
+const GPIO_INTERRUPT_ENABLE_SET: u8 = GpioRegister::InterruptEnableSet as u8;
+const GPIO_INTERRUPT_ENABLE_CLEAR: u8 = GpioRegister::InterruptEnableClear as u8;
+const GPIO_INTERRUPT_FLAG: u8 = GpioRegister::InterruptFlag as u8;
+const GPIO_PULL_ENABLE_SET: u8 = GpioRegister::PullEnableSet as u8;
+const GPIO_PULL_ENABLE_CLEAR: u8 = GpioRegister::PullEnableClear as u8;
+
+const KEYPAD_EVENT: u8 = KeypadRegister::Event as u8;
+const KEYPAD_INTERRUPT_ENABLE_SET: u8 = KeypadRegister::InterruptEnableSet as u8;
+const KEYPAD_INTERRUPT_ENABLE_CLEAR: u8 = KeypadRegister::InterruptEnableClear as u8;
+const KEYPAD_COUNT: u8 = KeypadRegister::Count as u8;
+const KEYPAD_FIFO: u8 = KeypadRegister::Fifo as u8;
+
+const NEOPIXEL_PIN: u8 = NeopixelRegister::Pin as u8;
+const NEOPIXEL_SPEED: u8 = NeopixelRegister::Speed as u8;
+const NEOPIXEL_BUFFER_LENGTH: u8 = NeopixelRegister::BufferLength as u8;
+const NEOPIXEL_BUFFER: u8 = NeopixelRegister::Buffer as u8;
+const NEOPIXEL_SHOW: u8 = NeopixelRegister::Show as u8;
+
+/// The keypad edges, in the order of their bits in the Seesaw event configuration.
+const EDGE_HIGH: u8 = 0;
+const EDGE_LOW: u8 = 1;
+const EDGE_FALLING: u8 = 2;
+const EDGE_RISING: u8 = 3;
+
+/// The NeoTrellis product code with a build date of 2021-06-14.
+const DEFAULT_VERSION: u32 = (3954 << 16) | (14 << 11) | (6 << 7) | 21;
+
+/// The status, GPIO, interrupt, EEPROM, NeoPixel and keypad modules.
+const DEFAULT_OPTIONS: u32 = (1 << 0x00) | (1 << 0x01) | (1 << 0x0B) | (1 << 0x0D) | (1 << 0x0E) | (1 << 0x10);
+
+/// 25.0 degrees Celsius in 16.16 fixed point.
+const DEFAULT_TEMPERATURE: u32 = 25 << 16;
+
+/// The number of events the Seesaw keypad FIFO can hold before dropping new ones.
+pub const FIFO_CAPACITY: usize = 32;
+
+/// A transaction recorded by the `SimulatedBus`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transaction {
+    /// Bytes written to the device at the given address.
+    Write(u8, Vec<u8>),
+
+    /// Bytes read from the device at the given address.
+    Read(u8, Vec<u8>)
+}
+
+/// A simulated NeoTrellis board.
+pub struct SimulatedNeotrellis {
+    address: u8,
+    hardware_id: u8,
+    version: u32,
+    options: u32,
+    temperature_raw: u32,
+    reset_count: usize,
+    register: (u8, u8),
+    gpio_direction: u32,
+    gpio_output: u32,
+    gpio_pull: u32,
+    gpio_interrupt: u32,
+    gpio_flags: u32,
+    gpio_inputs: u32,
+    gpio_driven: u32,
+    key_edges: [u8; 16],
+    pressed: [bool; 16],
+    fifo: VecDeque<u8>,
+    interrupt_enabled: bool,
+    neopixel_pin: Option<u8>,
+    neopixel_speed: Option<u8>,
+    neopixel_buffer: Vec<u8>,
+    neopixel_shown: Vec<u8>,
+    show_count: usize
+}
+
+impl SimulatedNeotrellis {
+    /// Creates a board at `address` in its power-on state.
+    pub fn new(address: u8) -> Self {
+        SimulatedNeotrellis {
+            address,
+            hardware_id: HARDWARE_ID,
+            version: DEFAULT_VERSION,
+            options: DEFAULT_OPTIONS,
+            temperature_raw: DEFAULT_TEMPERATURE,
+            reset_count: 0,
+            register: (0, 0),
+            gpio_direction: 0,
+            gpio_output: 0,
+            gpio_pull: 0,
+            gpio_interrupt: 0,
+            gpio_flags: 0,
+            gpio_inputs: 0,
+            gpio_driven: 0,
+            key_edges: [0; 16],
+            pressed: [false; 16],
+            fifo: VecDeque::new(),
+            interrupt_enabled: false,
+            neopixel_pin: None,
+            neopixel_speed: None,
+            neopixel_buffer: Vec::new(),
+            neopixel_shown: Vec::new(),
+            show_count: 0
+        }
+    }
+
+    /// The I2C address of the board.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Changes the hardware ID the board reports, to simulate another Seesaw product.
+    pub fn set_hardware_id(&mut self, hardware_id: u8) {
+        self.hardware_id = hardware_id;
+    }
+
+    /// Changes the raw value of the VERSION register: the product code in the high half and
+    /// the build date code in the low half.
+    pub fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    /// Changes the raw value of the OPTIONS register, where bit n is set for each module
+    /// base n that is available.
+    pub fn set_options(&mut self, options: u32) {
+        self.options = options;
+    }
+
+    /// Changes the raw value of the TEMP register, in 16.16 fixed point degrees Celsius.
+    pub fn set_temperature_raw(&mut self, temperature_raw: u32) {
+        self.temperature_raw = temperature_raw;
+    }
+
+    /// The number of software resets the board has received.
+    pub fn reset_count(&self) -> usize {
+        self.reset_count
+    }
+
+    /// Drives a GPIO pin from outside the board, e.g. with a button. An input pin reads this
+    /// level instead of its pull, and flags an interrupt if the level changes.
+    pub fn set_gpio_input(&mut self, pin: u8, high: bool) {
+        let bit = 1 << pin;
+        let before = self.gpio_levels();
+
+        self.gpio_driven |= bit;
+        if high { self.gpio_inputs |= bit; } else { self.gpio_inputs &= !bit; }
+        self.flag_gpio_changes(before);
+    }
+
+    /// Returns true if a GPIO pin is configured as an output.
+    pub fn is_gpio_output(&self, pin: u8) -> bool {
+        self.gpio_direction & (1 << pin) != 0
+    }
+
+    /// Returns true if the pull resistor of a GPIO pin is enabled.
+    pub fn is_gpio_pull_enabled(&self, pin: u8) -> bool {
+        self.gpio_pull & (1 << pin) != 0
+    }
+
+    /// The output level of a GPIO pin, which is also its pull direction when it is an input.
+    pub fn gpio_output(&self, pin: u8) -> bool {
+        self.gpio_output & (1 << pin) != 0
+    }
+
+    /// Presses a key, queueing the rising edge event and, if enabled, a high level event.
+    pub fn press(&mut self, key_index: usize) {
+        self.pressed[key_index] = true;
+        self.queue_event(key_index, EDGE_RISING);
+        self.queue_event(key_index, EDGE_HIGH);
+    }
+
+    /// Releases a key, queueing the falling edge event and, if enabled, a low level event.
+    pub fn release(&mut self, key_index: usize) {
+        self.pressed[key_index] = false;
+        self.queue_event(key_index, EDGE_FALLING);
+        self.queue_event(key_index, EDGE_LOW);
+    }
+
+    /// Returns true if the key is currently held down.
+    pub fn is_pressed(&self, key_index: usize) -> bool {
+        self.pressed[key_index]
+    }
+
+    /// Returns true if events are enabled for the given edge bit (0 high, 1 low, 2 falling,
+    /// 3 rising) of a key.
+    pub fn is_edge_enabled(&self, key_index: usize, edge: u8) -> bool {
+        self.key_edges[key_index] & (1 << edge) != 0
+    }
+
+    /// The raw events waiting in the keypad FIFO.
+    pub fn fifo(&self) -> &VecDeque<u8> {
+        &self.fifo
+    }
+
+    /// Returns true if the keypad interrupt has been enabled.
+    pub fn is_interrupt_enabled(&self) -> bool {
+        self.interrupt_enabled
+    }
+
+    /// Returns true if the board is pulling its INT line low.
+    pub fn interrupt_asserted(&self) -> bool {
+        (self.interrupt_enabled && !self.fifo.is_empty()) || self.gpio_flags != 0
+    }
+
+    /// The NeoPixel data pin, once it has been set.
+    pub fn neopixel_pin(&self) -> Option<u8> {
+        self.neopixel_pin
+    }
+
+    /// The NeoPixel speed setting, once it has been set; 1 for 800khz.
+    pub fn neopixel_speed(&self) -> Option<u8> {
+        self.neopixel_speed
+    }
+
+    /// The raw GRB bytes of the NeoPixel buffer.
+    pub fn neopixel_buffer(&self) -> &[u8] {
+        &self.neopixel_buffer
+    }
+
+    /// The RGB value of an LED in the NeoPixel buffer, whether or not it has been shown.
+    pub fn buffered_pixel(&self, led_index: usize) -> (u8, u8, u8) {
+        grb_pixel(&self.neopixel_buffer, led_index)
+    }
+
+    /// The RGB value an LED is displaying, as of the last show command.
+    pub fn pixel(&self, led_index: usize) -> (u8, u8, u8) {
+        grb_pixel(&self.neopixel_shown, led_index)
+    }
+
+    /// The number of show commands the board has received.
+    pub fn show_count(&self) -> usize {
+        self.show_count
+    }
+
+    /// The level every GPIO pin reads: outputs read their output level, and inputs read the
+    /// level they are driven to or their pull.
+    fn gpio_levels(&self) -> u32 {
+        let pulled = self.gpio_output & self.gpio_pull & !self.gpio_driven;
+        let inputs = (self.gpio_inputs & self.gpio_driven) | pulled;
+        (self.gpio_output & self.gpio_direction) | (inputs & !self.gpio_direction)
+    }
+
+    fn flag_gpio_changes(&mut self, before: u32) {
+        self.gpio_flags |= (before ^ self.gpio_levels()) & self.gpio_interrupt;
+    }
+
+    fn queue_event(&mut self, key_index: usize, edge: u8) {
+        if !self.is_edge_enabled(key_index, edge) || self.fifo.len() == FIFO_CAPACITY { return; }
+
+        self.fifo.push_back((neo_trellis_index(key_index) << 2) | edge);
+    }
+
+    /// Returns every module to its power-on state, keeping what is fixed in the firmware.
+    fn reset(&mut self) {
+        *self = SimulatedNeotrellis {
+            hardware_id: self.hardware_id,
+            version: self.version,
+            options: self.options,
+            temperature_raw: self.temperature_raw,
+            reset_count: self.reset_count + 1,
+            ..SimulatedNeotrellis::new(self.address)
+        };
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if bytes.len() < 2 { return; }
+
+        self.register = (bytes[0], bytes[1]);
+        let data = &bytes[2..];
+
+        match self.register {
+            (STATUS_REGISTER_BASE, STATUS_SOFTWARE_RESET) if data.first() == Some(&0xFF) => {
+                self.reset();
+            },
+            (GPIO_REGISTER_BASE, register) if data.len() >= 4 => {
+                let mask = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                let before = self.gpio_levels();
+
+                match register {
+                    GPIO_DIRECTION_SET => self.gpio_direction |= mask,
+                    GPIO_DIRECTION_CLEAR => self.gpio_direction &= !mask,
+                    GPIO_BULK => self.gpio_output = mask,
+                    GPIO_BULK_SET => self.gpio_output |= mask,
+                    GPIO_BULK_CLEAR => self.gpio_output &= !mask,
+                    GPIO_BULK_TOGGLE => self.gpio_output ^= mask,
+                    GPIO_INTERRUPT_ENABLE_SET => self.gpio_interrupt |= mask,
+                    GPIO_INTERRUPT_ENABLE_CLEAR => self.gpio_interrupt &= !mask,
+                    GPIO_PULL_ENABLE_SET => self.gpio_pull |= mask,
+                    GPIO_PULL_ENABLE_CLEAR => self.gpio_pull &= !mask,
+                    _ => {}
+                }
+
+                self.flag_gpio_changes(before);
+            },
+            (KEYPAD_REGISTER_BASE, KEYPAD_EVENT) if data.len() >= 2 => {
+                let key_index = see_saw_index(data[0]) as usize;
+                if key_index >= 16 { return; }
+
+                // The low bit enables or disables the edges given in the next four bits
+                let edges = (data[1] >> 1) & 0x0F;
+                if data[1] & 0x01 != 0 {
+                    self.key_edges[key_index] |= edges;
+                } else {
+                    self.key_edges[key_index] &= !edges;
+                }
+            },
+            (KEYPAD_REGISTER_BASE, KEYPAD_INTERRUPT_ENABLE_SET) if data.first() == Some(&0x01) => {
+                self.interrupt_enabled = true;
+            },
+            (KEYPAD_REGISTER_BASE, KEYPAD_INTERRUPT_ENABLE_CLEAR) if data.first() == Some(&0x01) => {
+                self.interrupt_enabled = false;
+            },
+            (NEOPIXEL_REGISTER_BASE, NEOPIXEL_PIN) if !data.is_empty() => {
+                self.neopixel_pin = Some(data[0]);
+            },
+            (NEOPIXEL_REGISTER_BASE, NEOPIXEL_SPEED) if !data.is_empty() => {
+                self.neopixel_speed = Some(data[0]);
+            },
+            (NEOPIXEL_REGISTER_BASE, NEOPIXEL_BUFFER_LENGTH) if data.len() >= 2 => {
+                let length = u16::from_be_bytes([data[0], data[1]]) as usize;
+                self.neopixel_buffer.resize(length, 0);
+                self.neopixel_shown.resize(length, 0);
+            },
+            (NEOPIXEL_REGISTER_BASE, NEOPIXEL_BUFFER) if data.len() >= 2 => {
+                // Bytes beyond the configured buffer length are ignored
+                let offset = u16::from_be_bytes([data[0], data[1]]) as usize;
+                for (i, &byte) in data[2..].iter().enumerate() {
+                    if let Some(slot) = self.neopixel_buffer.get_mut(offset + i) { *slot = byte; }
+                }
+            },
+            (NEOPIXEL_REGISTER_BASE, NEOPIXEL_SHOW) => {
+                self.neopixel_shown.clone_from(&self.neopixel_buffer);
+                self.show_count += 1;
+            },
+            _ => {}
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        buffer.fill(0);
+
+        match self.register {
+            (STATUS_REGISTER_BASE, STATUS_HARDWARE_ID) => {
+                if let Some(byte) = buffer.first_mut() { *byte = self.hardware_id; }
+            },
+            (STATUS_REGISTER_BASE, STATUS_VERSION) => copy_u32(buffer, self.version),
+            (STATUS_REGISTER_BASE, STATUS_OPTIONS) => copy_u32(buffer, self.options),
+            (STATUS_REGISTER_BASE, STATUS_TEMPERATURE) => copy_u32(buffer, self.temperature_raw),
+            (GPIO_REGISTER_BASE, GPIO_BULK) => copy_u32(buffer, self.gpio_levels()),
+            (GPIO_REGISTER_BASE, GPIO_INTERRUPT_FLAG) => {
+                copy_u32(buffer, self.gpio_flags);
+                self.gpio_flags = 0;
+            },
+            (KEYPAD_REGISTER_BASE, KEYPAD_COUNT) => {
+                if let Some(byte) = buffer.first_mut() { *byte = self.fifo.len() as u8; }
+            },
+            (KEYPAD_REGISTER_BASE, KEYPAD_FIFO) => {
+                // Reading past the queued events gives empty slots
+                for byte in buffer.iter_mut() {
+                    *byte = self.fifo.pop_front().unwrap_or(0xFF);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn copy_u32(buffer: &mut [u8], value: u32) {
+    for (byte, value_byte) in buffer.iter_mut().zip(value.to_be_bytes()) { *byte = value_byte; }
+}
+
+fn grb_pixel(buffer: &[u8], led_index: usize) -> (u8, u8, u8) {
+    let offset = led_index * 3;
+    match buffer.get(offset..offset + 3) {
+        Some(grb) => (grb[1], grb[0], grb[2]),
+        None => (0, 0, 0)
+    }
+}
+
+/// A simulated I2C bus with NeoTrellis boards attached.
+///
+/// Transactions to addresses without a board fail with a `NoAcknowledge` error.
+pub struct SimulatedBus {
+    boards: Vec<SimulatedNeotrellis>,
+    transactions: Vec<Transaction>
+}
+
+impl SimulatedBus {
+    /// Creates a bus with a board in its power-on state at each of the `addresses`.
+    pub fn new(addresses: &[u8]) -> Self {
+        SimulatedBus {
+            boards: addresses.iter().map(|&address| SimulatedNeotrellis::new(address)).collect(),
+            transactions: Vec::new()
+        }
+    }
+
+    /// The board at `address`, if there is one.
+    pub fn board(&self, address: u8) -> Option<&SimulatedNeotrellis> {
+        self.boards.iter().find(|board| board.address == address)
+    }
+
+    /// The board at `address`, if there is one.
+    pub fn board_mut(&mut self, address: u8) -> Option<&mut SimulatedNeotrellis> {
+        self.boards.iter_mut().find(|board| board.address == address)
+    }
+
+    /// Every transaction made on the bus so far, in order.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Forgets the recorded transactions.
+    pub fn clear_transactions(&mut self) {
+        self.transactions.clear();
+    }
+
+    /// Returns true if any board is pulling the shared INT line low.
+    pub fn interrupt_asserted(&self) -> bool {
+        self.boards.iter().any(SimulatedNeotrellis::interrupt_asserted)
+    }
+}
+
+impl ErrorType for SimulatedBus {
+    type Error = ErrorKind;
+}
+
+impl I2c for SimulatedBus {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let board = self.boards
+            .iter_mut()
+            .find(|board| board.address == address)
+            .ok_or(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))?;
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    board.write(bytes);
+                    self.transactions.push(Transaction::Write(address, bytes.to_vec()));
+                },
+                Operation::Read(buffer) => {
+                    board.read(buffer);
+                    self.transactions.push(Transaction::Read(address, buffer.to_vec()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for SimulatedBus {
+    async fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        I2c::transaction(self, address, operations)
+    }
+}
+
+/// A simulated input pin for the INT line, set by the test.
+pub struct SimulatedPin {
+    low: bool
+}
+
+impl SimulatedPin {
+    /// Creates a pin that reads high, like an idle INT line.
+    pub fn new() -> Self {
+        SimulatedPin { low: false }
+    }
+
+    /// Sets whether the pin reads low, e.g. from `SimulatedBus::interrupt_asserted`.
+    pub fn set_low(&mut self, low: bool) {
+        self.low = low;
+    }
+}
+
+impl Default for SimulatedPin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl embedded_hal::digital::ErrorType for SimulatedPin {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::digital::InputPin for SimulatedPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.low)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.low)
+    }
+}
+
+/// A delay provider that returns immediately, since the simulated boards answer at once.
+pub struct NoDelay;
+
+impl embedded_hal::delay::DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Fixtures shared by the unit tests of the crate.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use core::cell::RefCell;
+
+    use embedded_hal_bus::i2c::RefCellDevice;
+
+    use super::{NoDelay, SimulatedBus};
+    use crate::{MultiTrellis, Neotrellis, NeotrellisEvent, NeotrellisEventType};
+
+    /// A bus with one board at the default address.
+    pub(crate) fn single_board_bus() -> RefCell<SimulatedBus> {
+        RefCell::new(SimulatedBus::new(&[crate::DEFAULT_ADDRESS]))
+    }
+
+    /// A bus with a board at each of the `addresses` of a MultiTrellis grid.
+    pub(crate) fn multi_board_bus<const ROWS: usize, const COLS: usize>(addresses: [[u8; COLS]; ROWS]) -> RefCell<SimulatedBus> {
+        RefCell::new(SimulatedBus::new(&addresses.concat()))
+    }
+
+    /// A bus with one board at the default address that the async driver has initialized,
+    /// with the transactions of the initialization cleared.
+    ///
+    /// The async driver borrows the bus for as long as it is used, so the async tests make
+    /// a new driver whenever they need to look at the bus in between; the board keeps its
+    /// state.
+    #[cfg(feature = "async")]
+    pub(crate) fn async_initialized_bus() -> SimulatedBus {
+        let mut bus = SimulatedBus::new(&[crate::DEFAULT_ADDRESS]);
+        embassy_futures::block_on(crate::asynch::Neotrellis::new(&mut bus, NoDelay, None).initialize()).unwrap();
+        bus.clear_transactions();
+        bus
+    }
+
+    /// An initialized Neotrellis at the default address of `bus`, with the transactions of
+    /// the initialization cleared.
+    pub(crate) fn neotrellis(bus: &RefCell<SimulatedBus>) -> Neotrellis<RefCellDevice<'_, SimulatedBus>, NoDelay> {
+        let mut nt = Neotrellis::new(RefCellDevice::new(bus), NoDelay, None);
+        nt.initialize().unwrap();
+        bus.borrow_mut().clear_transactions();
+        nt
+    }
+
+    /// An initialized MultiTrellis of the boards of `bus` at `addresses`.
+    pub(crate) fn multitrellis<const ROWS: usize, const COLS: usize>(
+        bus: &RefCell<SimulatedBus>,
+        addresses: [[u8; COLS]; ROWS]
+    ) -> MultiTrellis<RefCellDevice<'_, SimulatedBus>, NoDelay, ROWS, COLS> {
+        let mut mt = MultiTrellis::new(RefCellDevice::new(bus), NoDelay, addresses);
+        mt.initialize().unwrap();
+        mt
+    }
+
+    /// A `KeyPress` event of `key_index`.
+    pub(crate) fn press(key_index: usize) -> NeotrellisEvent {
+        NeotrellisEvent { key_index, event_type: NeotrellisEventType::KeyPress }
+    }
+
+    /// A `KeyRelease` event of `key_index`.
+    pub(crate) fn release(key_index: usize) -> NeotrellisEvent {
+        NeotrellisEvent { key_index, event_type: NeotrellisEventType::KeyRelease }
+    }
+}
@@ -0,0 +1,87 @@
+//! `smart-leds` interoperability, which requires the `smart-leds-trait` feature.
+//!
+//! `Neotrellis` implements `SmartLedsWrite` with `RGB8` pixels, so the animation and effect
+//! crates of the `smart-leds` ecosystem can drive the grid unchanged. `RGB8` and `Color`
+//! convert into each other, so either can be given to the LED functions.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::{Color, Neotrellis, NeotrellisError};
+
+impl From<RGB8> for Color {
+    fn from(pixel: RGB8) -> Self {
+        Color::rgb(pixel.r, pixel.g, pixel.b)
+    }
+}
+
+impl From<Color> for RGB8 {
+    fn from(color: Color) -> Self {
+        RGB8::new(color.r, color.g, color.b)
+    }
+}
+
+impl <I2C: I2c, D: DelayNs> SmartLedsWrite for Neotrellis<I2C, D> {
+    type Error = NeotrellisError<I2C::Error>;
+    type Color = RGB8;
+
+    /// Sets the LEDs from index 0 up to the pixels of `iterator` and shows them, with
+    /// `flush`. LEDs past the end of a shorter iterator keep their color, and pixels past
+    /// the 16th are ignored.
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>
+    {
+        for (index, pixel) in iterator.into_iter().take(16).enumerate() {
+            self.set_pixel(index as u8, Color::from(pixel.into()))?;
+        }
+
+        self.flush()
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> smart_leds_trait::SmartLedsWriteAsync for crate::asynch::Neotrellis<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs
+{
+    type Error = NeotrellisError<I2C::Error>;
+    type Color = RGB8;
+
+    /// Async counterpart of the blocking `SmartLedsWrite` implementation.
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<RGB8>
+    {
+        for (index, pixel) in iterator.into_iter().take(16).enumerate() {
+            self.set_pixel(index as u8, Color::from(pixel.into()))?;
+        }
+
+        self.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+
+    #[test]
+    fn write_sets_and_shows_the_pixels_in_order() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+
+        let rainbow = (0..20).map(|i| RGB8::new(i * 10, 0, 255 - i * 10));
+        SmartLedsWrite::write(&mut nt, rainbow).unwrap();
+
+        let bus = bus.borrow();
+        let board = bus.board(0x2E).unwrap();
+        assert_eq!(board.pixel(0), (0, 0, 255));
+        assert_eq!(board.pixel(15), (150, 0, 105));
+        assert_eq!(nt.pixel(3), Some(Color::from(RGB8::new(30, 0, 225))));
+    }
+}
@@ -0,0 +1,226 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::seesaw::{read_u32, write, Seesaw, StatusRegister, STATUS_REGISTER_BASE};
+use crate::NeotrellisError;
+#[cfg(feature = "async")]
+use crate::asynch::seesaw as asynch;
+
+/// The firmware version reported by the Seesaw VERSION register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FirmwareVersion {
+    /// The Adafruit product code of the board; 3954 for the NeoTrellis.
+    pub product_code: u16,
+
+    /// The year the firmware was built, counted from 2000.
+    pub year: u8,
+
+    /// The month the firmware was built, 1-12.
+    pub month: u8,
+
+    /// The day of the month the firmware was built, 1-31.
+    pub day: u8
+}
+
+impl From<u32> for FirmwareVersion {
+    fn from(raw: u32) -> Self {
+        // The low half is a date code: 5 bits of day, 4 bits of month, a reserved bit
+        // and 6 bits of year, from the top down
+        FirmwareVersion {
+            product_code: (raw >> 16) as u16,
+            year: (raw & 0x3F) as u8,
+            month: ((raw >> 7) & 0x0F) as u8,
+            day: ((raw >> 11) & 0x1F) as u8
+        }
+    }
+}
+
+/// The set of Seesaw modules compiled into the board firmware, from the OPTIONS register.
+///
+/// Bit n is set if the module with register base n is available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options(pub u32);
+
+impl Options {
+    /// The status module, which is always available.
+    pub const STATUS: Options = Options(1 << 0x00);
+
+    /// The GPIO module.
+    pub const GPIO: Options = Options(1 << 0x01);
+
+    /// The first serial communication module.
+    pub const SERCOM0: Options = Options(1 << 0x02);
+
+    /// The PWM timer module.
+    pub const TIMER: Options = Options(1 << 0x08);
+
+    /// The analog input module.
+    pub const ADC: Options = Options(1 << 0x09);
+
+    /// The analog output module.
+    pub const DAC: Options = Options(1 << 0x0A);
+
+    /// The interrupt module.
+    pub const INTERRUPT: Options = Options(1 << 0x0B);
+
+    /// The debug access port module.
+    pub const DAP: Options = Options(1 << 0x0C);
+
+    /// The EEPROM module.
+    pub const EEPROM: Options = Options(1 << 0x0D);
+
+    /// The NeoPixel module that drives the LEDs.
+    pub const NEOPIXEL: Options = Options(1 << 0x0E);
+
+    /// The capacitive touch module.
+    pub const TOUCH: Options = Options(1 << 0x0F);
+
+    /// The keypad module that scans the keys.
+    pub const KEYPAD: Options = Options(1 << 0x10);
+
+    /// The rotary encoder module.
+    pub const ENCODER: Options = Options(1 << 0x11);
+
+    /// Returns true if every module in `other` is also in this set.
+    pub fn contains(self, other: Options) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for Options {
+    type Output = Options;
+
+    fn bitor(self, other: Options) -> Options {
+        Options(self.0 | other.0)
+    }
+}
+
+impl <I2C: I2c, D: DelayNs> Seesaw<I2C, D> {
+    /// Delay-based function that reads the product code and build date of the board firmware.
+    pub fn firmware_version(&mut self) -> Result<FirmwareVersion, NeotrellisError<I2C::Error>> {
+        let raw = read_status_u32(&mut self.i2c, self.address, &mut self.delay, StatusRegister::Version)?;
+        Ok(FirmwareVersion::from(raw))
+    }
+
+    /// Delay-based function that reads which Seesaw modules the board firmware was built with.
+    pub fn options(&mut self) -> Result<Options, NeotrellisError<I2C::Error>> {
+        read_status_u32(&mut self.i2c, self.address, &mut self.delay, StatusRegister::Options).map(Options)
+    }
+
+    /// Delay-based function that reads the temperature of the Seesaw chip in degrees Celsius.
+    /// The sensor is not calibrated, so it is only accurate to a few degrees.
+    pub fn temperature_c(&mut self) -> Result<f32, NeotrellisError<I2C::Error>> {
+        let raw = read_status_u32(&mut self.i2c, self.address, &mut self.delay, StatusRegister::Temperature)?;
+        Ok(temperature_c(raw))
+    }
+
+    /// Delay-based function that restarts the Seesaw firmware, which recovers a board that has
+    /// stopped responding without a power cycle. Every setting is lost, so `initialize` must be
+    /// called again before the keypad and LEDs are used.
+    pub fn software_reset(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        write(&mut self.i2c, self.address, &software_reset_command())?;
+        self.delay.delay_ms(RESET_TIME_MS);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl <I2C, D> crate::asynch::Seesaw<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs
+{
+    /// Reads the product code and build date of the board firmware.
+    pub async fn firmware_version(&mut self) -> Result<FirmwareVersion, NeotrellisError<I2C::Error>> {
+        let raw = self.read_status_u32(StatusRegister::Version).await?;
+        Ok(FirmwareVersion::from(raw))
+    }
+
+    /// Reads which Seesaw modules the board firmware was built with.
+    pub async fn options(&mut self) -> Result<Options, NeotrellisError<I2C::Error>> {
+        self.read_status_u32(StatusRegister::Options).await.map(Options)
+    }
+
+    /// Reads the temperature of the Seesaw chip in degrees Celsius. The sensor is not
+    /// calibrated, so it is only accurate to a few degrees.
+    pub async fn temperature_c(&mut self) -> Result<f32, NeotrellisError<I2C::Error>> {
+        let raw = self.read_status_u32(StatusRegister::Temperature).await?;
+        Ok(temperature_c(raw))
+    }
+
+    /// Restarts the Seesaw firmware. Every setting is lost, so `initialize` must be called
+    /// again before the keypad and LEDs are used.
+    pub async fn software_reset(&mut self) -> Result<(), NeotrellisError<I2C::Error>> {
+        asynch::write(&mut self.i2c, self.address, &software_reset_command()).await?;
+        self.delay.delay_ms(RESET_TIME_MS).await;
+        Ok(())
+    }
+
+    async fn read_status_u32(&mut self, register: StatusRegister) -> Result<u32, NeotrellisError<I2C::Error>> {
+        asynch::write(&mut self.i2c, self.address, &[STATUS_REGISTER_BASE, register as u8]).await?;
+        self.delay.delay_us(STATUS_READ_DELAY_US).await;
+        asynch::read_u32(&mut self.i2c, self.address).await
+    }
+}
+
+/// The time the Seesaw takes to restart after a software reset.
+const RESET_TIME_MS: u32 = 10;
+
+/// The time the Seesaw takes to prepare a 4 byte status register; longer than most
+/// registers because the temperature is sampled on demand.
+const STATUS_READ_DELAY_US: u32 = 1000;
+
+fn temperature_c(raw: u32) -> f32 {
+    // 16.16 fixed point, with the top two bits unused
+    (raw & 0x3FFF_FFFF) as f32 / 65536.0
+}
+
+fn software_reset_command() -> [u8; 3] {
+    [STATUS_REGISTER_BASE, StatusRegister::SoftwareReset as u8, 0xFF]
+}
+
+fn read_status_u32<I2C: I2c>(i2c: &mut I2C, address: u8, delay: &mut impl DelayNs, register: StatusRegister) -> Result<u32, NeotrellisError<I2C::Error>> {
+    write(i2c, address, &[STATUS_REGISTER_BASE, register as u8])?;
+    delay.delay_us(STATUS_READ_DELAY_US);
+    read_u32(i2c, address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::fixtures::{neotrellis, single_board_bus};
+
+    #[test]
+    fn reads_version_options_and_temperature() {
+        let bus = single_board_bus();
+        bus.borrow_mut().board_mut(0x2E).unwrap().set_version((3954 << 16) | (14 << 11) | (6 << 7) | 21);
+        bus.borrow_mut().board_mut(0x2E).unwrap().set_temperature_raw(0xC019_8000);
+
+        let mut nt = neotrellis(&bus);
+        assert_eq!(nt.firmware_version(), Ok(FirmwareVersion { product_code: 3954, year: 21, month: 6, day: 14 }));
+        assert_eq!(nt.temperature_c(), Ok(25.5));
+
+        let options = nt.options().unwrap();
+        assert!(options.contains(Options::STATUS | Options::NEOPIXEL | Options::KEYPAD));
+        assert!(!options.contains(Options::ENCODER));
+    }
+
+    #[test]
+    fn software_reset_returns_the_board_to_its_power_on_state() {
+        let bus = single_board_bus();
+        let mut nt = neotrellis(&bus);
+        nt.enable_key_interrupt().unwrap();
+
+        nt.software_reset().unwrap();
+        {
+            let bus = bus.borrow();
+            let board = bus.board(0x2E).unwrap();
+            assert_eq!(board.reset_count(), 1);
+            assert!(!board.is_interrupt_enabled());
+            assert_eq!(board.neopixel_pin(), None);
+        }
+
+        nt.initialize().unwrap();
+        assert_eq!(bus.borrow().board(0x2E).unwrap().neopixel_pin(), Some(3));
+    }
+}
@@ -0,0 +1,287 @@
+//! Text drawn with a tiny built-in bitmap font, and a scrolling `Marquee`.
+//!
+//! Each character is 3 LEDs wide and 4 tall, so a 4x4 board shows one character with a
+//! column to spare and a row of boards shows one per board. The font has the digits, the
+//! letters (lowercase letters are drawn as uppercase), the space and `-.:!+/`; other
+//! characters are drawn as a space.
+//!
+//! Text is drawn into any `Leds` buffer laid out in rows `columns` LEDs wide, like the
+//! effects of the `animation` module. For a `MultiTrellis`, render into a `[Color; N]` array
+//! of its `WIDTH * HEIGHT` LEDs and send it with `set_leds`.
+//!
+//! Example usage: scrolling the tempo across a row of two boards:
+//! ```ignore
+//! let mut marquee = Marquee::new(Color::CYAN, MultiTrellis::<_, _, 1, 2>::WIDTH, 120);
+//! write!(marquee, "{} BPM", tempo).unwrap();
+//!
+//! let mut frame = [Color::BLACK; 8 * 4];
+//! loop {
+//!     marquee.tick(timer.now_ms(), &mut frame);
+//!     mt.set_leds(&frame).unwrap();
+//!     mt.delay.delay_us(300);
+//!     mt.refresh_leds().unwrap();
+//! }
+//! ```
+
+use core::fmt;
+
+use crate::animation::{Effect, Leds};
+use crate::Color;
+
+/// The width of a character, in LEDs.
+pub const GLYPH_WIDTH: usize = 3;
+
+/// The height of a character, in LEDs.
+pub const GLYPH_HEIGHT: usize = 4;
+
+/// The most characters a `Marquee` holds.
+pub const MARQUEE_CAPACITY: usize = 32;
+
+/// Returns the rows of a character in the built-in font, from the top down. Bit 2 of each
+/// row is its left column and bit 0 its right column. Returns `None` for characters that
+/// are not in the font.
+pub fn glyph(character: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b111],
+        '3' => [0b111, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001],
+        '5' => [0b111, 0b100, 0b011, 0b110],
+        '6' => [0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001],
+        'A' => [0b010, 0b101, 0b111, 0b101],
+        'B' => [0b110, 0b111, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b111],
+        'H' => [0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b110, 0b110, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b111],
+        'M' => [0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b010],
+        'P' => [0b111, 0b101, 0b111, 0b100],
+        'Q' => [0b010, 0b101, 0b111, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101],
+        'S' => [0b011, 0b100, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111],
+        'X' => [0b101, 0b010, 0b010, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010],
+        'Z' => [0b111, 0b011, 0b110, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010],
+        '!' => [0b010, 0b010, 0b000, 0b010],
+        '+' => [0b000, 0b010, 0b111, 0b010],
+        '/' => [0b001, 0b010, 0b010, 0b100],
+        _ => return None
+    };
+
+    Some(rows)
+}
+
+/// The width of `text` in LEDs, with a blank column between characters.
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1)
+}
+
+/// Draws `text` in `color` with the top left of its first character at (x, y) of a grid
+/// `columns` LEDs wide. Only the lit LEDs of each character are set, and the parts of the
+/// text outside of the grid are skipped, so `x` can be negative while text scrolls in.
+pub fn draw_text(leds: &mut dyn Leds, columns: usize, x: i32, y: i32, text: &str, color: Color) {
+    let columns = columns.max(1) as i32;
+    let rows = (leds.led_count() as i32 + columns - 1) / columns;
+
+    for (position, character) in text.chars().enumerate() {
+        let left = x + position as i32 * (GLYPH_WIDTH as i32 + 1);
+        if left >= columns { break; }
+        if left + (GLYPH_WIDTH as i32) <= 0 { continue; }
+
+        let glyph = match glyph(character) {
+            Some(glyph) => glyph,
+            None => continue
+        };
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                let (led_x, led_y) = (left + column as i32, y + row as i32);
+                let lit = bits & (0b100 >> column) != 0;
+                if !lit || !(0..columns).contains(&led_x) || !(0..rows).contains(&led_y) { continue; }
+
+                leds.set_color((led_y * columns + led_x) as usize, color);
+            }
+        }
+    }
+}
+
+/// Scrolls a line of text from right to left across a grid `columns` LEDs wide, one column
+/// every `step_ms`. The text starts just past the right edge at the first tick and comes
+/// round again once it has left on the left.
+///
+/// The text is set with `set_text` or written with `write!`, which appends to it, and is
+/// cut off after `MARQUEE_CAPACITY` characters. Characters other than ASCII are drawn as a
+/// space.
+pub struct Marquee {
+    /// The color of the text.
+    pub color: Color,
+
+    /// The color of the LEDs around the text.
+    pub background: Color,
+
+    /// The number of LEDs in each row of the grid.
+    pub columns: usize,
+
+    /// The row of the grid the top of the text is drawn on.
+    pub row: usize,
+
+    /// The time the text takes to move one column.
+    pub step_ms: u32,
+
+    text: [u8; MARQUEE_CAPACITY],
+    length: usize,
+    started_at: Option<u32>
+}
+
+impl Marquee {
+    /// Creates an empty marquee on a black background along the top of the grid.
+    pub fn new(color: Color, columns: usize, step_ms: u32) -> Self {
+        Marquee {
+            color,
+            background: Color::BLACK,
+            columns,
+            row: 0,
+            step_ms,
+            text: [b' '; MARQUEE_CAPACITY],
+            length: 0,
+            started_at: None
+        }
+    }
+
+    /// Replaces the text and starts scrolling it in again at the next tick.
+    pub fn set_text(&mut self, text: &str) {
+        self.length = 0;
+        self.push_str(text);
+        self.restart();
+    }
+
+    /// The text being scrolled.
+    pub fn text(&self) -> &str {
+        core::str::from_utf8(&self.text[..self.length]).unwrap_or_default()
+    }
+
+    /// Starts scrolling the text in again at the next tick.
+    pub fn restart(&mut self) {
+        self.started_at = None;
+    }
+
+    fn push_str(&mut self, text: &str) {
+        for character in text.chars() {
+            if self.length == MARQUEE_CAPACITY { return; }
+
+            self.text[self.length] = if character.is_ascii() { character as u8 } else { b' ' };
+            self.length += 1;
+        }
+    }
+}
+
+impl fmt::Write for Marquee {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.push_str(text);
+        Ok(())
+    }
+}
+
+impl Effect for Marquee {
+    fn tick(&mut self, now_ms: u32, leds: &mut dyn Leds) {
+        for index in 0..leds.led_count() { leds.set_color(index, self.background); }
+
+        let columns = self.columns.max(1);
+        let started_at = *self.started_at.get_or_insert(now_ms);
+        let travel = columns + text_width(self.text());
+        let offset = (now_ms.wrapping_sub(started_at) / self.step_ms.max(1)) as usize % travel;
+
+        let x = columns as i32 - offset as i32;
+        draw_text(leds, columns, x, self.row as i32, self.text(), self.color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::*;
+    use crate::sim::fixtures::{multi_board_bus, multitrellis};
+
+    fn lit_rows(leds: &[Color], columns: usize) -> std::vec::Vec<std::string::String> {
+        leds.chunks(columns)
+            .map(|row| row.iter().map(|&color| if color == Color::BLACK { '.' } else { '#' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn text_is_drawn_and_clipped_to_the_grid() {
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('~'), None);
+        assert_eq!(text_width("120"), 11);
+        assert_eq!(text_width(""), 0);
+
+        let mut leds = [Color::BLACK; 6 * 4];
+        draw_text(&mut leds, 6, -2, 0, "12", Color::WHITE);
+        assert_eq!(lit_rows(&leds, 6), [
+            "..##..",
+            "....#.",
+            "...#..",
+            "#.###."
+        ]);
+    }
+
+    #[test]
+    fn marquee_scrolls_across_a_tiled_grid() {
+        let bus = multi_board_bus([[0x2E, 0x2F]]);
+        let mut mt = multitrellis(&bus, [[0x2E, 0x2F]]);
+
+        let mut marquee = Marquee::new(Color::RED, 8, 100);
+        write!(marquee, "{}", 7).unwrap();
+        assert_eq!(marquee.text(), "7");
+
+        let mut frame = [Color::BLACK; 8 * 4];
+        marquee.tick(1_000, &mut frame);
+        assert!(frame.iter().all(|&color| color == Color::BLACK));
+
+        marquee.tick(1_600, &mut frame);
+        assert_eq!(lit_rows(&frame, 8), [
+            "..###...",
+            "....#...",
+            "...#....",
+            "...#...."
+        ]);
+
+        mt.set_leds(&frame).unwrap();
+        mt.refresh_leds().unwrap();
+        assert_eq!(bus.borrow().board(0x2E).unwrap().pixel(2), (255, 0, 0));
+        assert_eq!(bus.borrow().board(0x2F).unwrap().pixel(0), (255, 0, 0));
+
+        // Once the text has left the grid it comes round again
+        marquee.tick(2_100, &mut frame);
+        assert!(frame.iter().all(|&color| color == Color::BLACK));
+        marquee.tick(2_200, &mut frame);
+        assert_eq!(frame[7], Color::RED);
+
+        // An empty marquee on a grid with no columns draws nothing
+        let mut empty = Marquee::new(Color::RED, 0, 0);
+        empty.tick(0, &mut frame);
+        empty.tick(50, &mut frame);
+        assert!(frame.iter().all(|&color| color == Color::BLACK));
+    }
+}